@@ -1,5 +1,7 @@
 use crate::*;
-use alloc::alloc::{alloc, dealloc, Layout, LayoutError};
+use alloc::alloc::{alloc, alloc_zeroed, dealloc, realloc, GlobalAlloc, Layout, LayoutError};
+use core::ffi::CStr;
+use core::mem::MaybeUninit;
 use core::ptr::NonNull;
 
 #[derive(Debug)]
@@ -81,6 +83,12 @@ pub unsafe trait Allocator {
         Ok(new_ptr)
     }
 
+    /// Whether `ptr` was handed out by this allocator. Allocators that can't
+    /// tell always answer `false`.
+    fn owns(&self, ptr: NonNull<u8>) -> bool {
+        return false;
+    }
+
     fn by_ref(&self) -> &Self
     where
         Self: Sized,
@@ -92,20 +100,209 @@ pub unsafe trait Allocator {
 #[derive(Clone, Copy)]
 pub struct Global;
 
+// Forwards to whatever `#[global_allocator]` is registered, so that `Global`
+// can share its implementation with allocators built on other `GlobalAlloc`s.
+struct RegisteredHeap;
+
+unsafe impl GlobalAlloc for RegisteredHeap {
+    #[inline]
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        return alloc(layout);
+    }
+
+    #[inline]
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        return alloc_zeroed(layout);
+    }
+
+    #[inline]
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        dealloc(ptr, layout);
+    }
+
+    #[inline]
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        return realloc(ptr, layout, new_size);
+    }
+}
+
+#[inline]
+fn dangling(layout: Layout) -> NonNull<[u8]> {
+    // Any non-null pointer with the right alignment is a valid zero-size
+    // allocation
+    let ptr = layout.align() as *mut u8;
+    let slice = core::ptr::slice_from_raw_parts_mut(ptr, 0);
+
+    return unsafe { NonNull::new_unchecked(slice) };
+}
+
+#[inline]
+pub(crate) fn heap_allocate<G>(
+    heap: &G,
+    layout: Layout,
+    zeroed: bool,
+) -> Result<NonNull<[u8]>, AllocError>
+where
+    G: GlobalAlloc,
+{
+    if layout.size() == 0 {
+        return Ok(dangling(layout));
+    }
+
+    let data = unsafe {
+        if zeroed {
+            heap.alloc_zeroed(layout)
+        } else {
+            heap.alloc(layout)
+        }
+    };
+
+    let data = NonNull::new(data).ok_or(AllocError)?;
+    let data = core::ptr::slice_from_raw_parts_mut(data.as_ptr(), layout.size());
+
+    return Ok(unsafe { NonNull::new_unchecked(data) });
+}
+
+#[inline]
+pub(crate) unsafe fn heap_deallocate<G>(heap: &G, ptr: NonNull<u8>, layout: Layout)
+where
+    G: GlobalAlloc,
+{
+    if layout.size() != 0 {
+        heap.dealloc(ptr.as_ptr(), layout);
+    }
+}
+
+#[inline]
+pub(crate) unsafe fn heap_grow<G>(
+    heap: &G,
+    ptr: NonNull<u8>,
+    old_layout: Layout,
+    new_layout: Layout,
+    zeroed: bool,
+) -> Result<NonNull<[u8]>, AllocError>
+where
+    G: GlobalAlloc,
+{
+    debug_assert!(
+        new_layout.size() >= old_layout.size(),
+        "`new_layout.size()` must be greater than or equal to `old_layout.size()`"
+    );
+
+    let (old_size, new_size) = (old_layout.size(), new_layout.size());
+
+    if old_size == 0 {
+        return heap_allocate(heap, new_layout, zeroed);
+    }
+
+    // The system realloc can only keep the alignment the block was created
+    // with, so anything else has to go through a fresh allocation.
+    if old_layout.align() != new_layout.align() {
+        let new_ptr = heap_allocate(heap, new_layout, zeroed)?;
+
+        core::ptr::copy_nonoverlapping(ptr.as_ptr(), new_ptr.as_ptr() as *mut u8, old_size);
+        heap_deallocate(heap, ptr, old_layout);
+
+        return Ok(new_ptr);
+    }
+
+    let data = heap.realloc(ptr.as_ptr(), old_layout, new_size);
+    let data = NonNull::new(data).ok_or(AllocError)?;
+
+    if zeroed {
+        data.as_ptr()
+            .add(old_size)
+            .write_bytes(0, new_size - old_size);
+    }
+
+    let data = core::ptr::slice_from_raw_parts_mut(data.as_ptr(), new_size);
+
+    return Ok(NonNull::new_unchecked(data));
+}
+
+#[inline]
+pub(crate) unsafe fn heap_shrink<G>(
+    heap: &G,
+    ptr: NonNull<u8>,
+    old_layout: Layout,
+    new_layout: Layout,
+) -> Result<NonNull<[u8]>, AllocError>
+where
+    G: GlobalAlloc,
+{
+    debug_assert!(
+        new_layout.size() <= old_layout.size(),
+        "`new_layout.size()` must be smaller than or equal to `old_layout.size()`"
+    );
+
+    let new_size = new_layout.size();
+
+    if new_size == 0 {
+        heap_deallocate(heap, ptr, old_layout);
+
+        return Ok(dangling(new_layout));
+    }
+
+    if old_layout.align() != new_layout.align() {
+        let new_ptr = heap_allocate(heap, new_layout, false)?;
+
+        core::ptr::copy_nonoverlapping(ptr.as_ptr(), new_ptr.as_ptr() as *mut u8, new_size);
+        heap_deallocate(heap, ptr, old_layout);
+
+        return Ok(new_ptr);
+    }
+
+    let data = heap.realloc(ptr.as_ptr(), old_layout, new_size);
+    let data = NonNull::new(data).ok_or(AllocError)?;
+    let data = core::ptr::slice_from_raw_parts_mut(data.as_ptr(), new_size);
+
+    return Ok(NonNull::new_unchecked(data));
+}
+
 unsafe impl Allocator for Global {
+    #[inline]
     fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
-        unsafe {
-            let mut data = alloc(layout);
+        return heap_allocate(&RegisteredHeap, layout, false);
+    }
 
-            let data = unwrap(data.as_mut());
-            let data = core::slice::from_raw_parts_mut(data, layout.size());
+    #[inline]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        return heap_allocate(&RegisteredHeap, layout, true);
+    }
 
-            return Ok(NonNull::new_unchecked(data));
-        }
+    #[inline]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        heap_deallocate(&RegisteredHeap, ptr, layout);
+    }
+
+    #[inline]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return heap_grow(&RegisteredHeap, ptr, old_layout, new_layout, false);
     }
 
-    unsafe fn deallocate(&self, mut ptr: NonNull<u8>, layout: Layout) {
-        dealloc(ptr.as_mut(), layout);
+    #[inline]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return heap_grow(&RegisteredHeap, ptr, old_layout, new_layout, true);
+    }
+
+    #[inline]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return heap_shrink(&RegisteredHeap, ptr, old_layout, new_layout);
     }
 }
 
@@ -157,6 +354,11 @@ where
     ) -> Result<NonNull<[u8]>, AllocError> {
         (**self).shrink(ptr, old_layout, new_layout)
     }
+
+    #[inline]
+    fn owns(&self, ptr: NonNull<u8>) -> bool {
+        (**self).owns(ptr)
+    }
 }
 
 impl<A> AllocExt for A where A: Allocator {}
@@ -166,8 +368,10 @@ pub trait AllocStat: Allocator {
     fn total_capacity(&self) -> usize;
 }
 
+// Handing out `&mut` from `&self` is the whole point of an arena
+#[allow(clippy::mut_from_ref)]
 pub trait AllocExt: Allocator {
-    fn new<T>(&self, t: T) -> &'static mut T {
+    fn new<T>(&self, t: T) -> &mut T {
         use alloc::alloc::Layout;
 
         let layout = Layout::for_value(&t);
@@ -181,7 +385,7 @@ pub trait AllocExt: Allocator {
         }
     }
 
-    fn add_slice<T>(&self, slice: &[T]) -> &'static mut [T]
+    fn add_slice<T>(&self, slice: &[T]) -> &mut [T]
     where
         T: Copy,
     {
@@ -204,8 +408,321 @@ pub trait AllocExt: Allocator {
         }
     }
 
-    fn add_str(&self, string: &str) -> &'static mut str {
+    fn add_str(&self, string: &str) -> &mut str {
         let string = string.as_bytes();
         return unsafe { core::str::from_utf8_unchecked_mut(self.add_slice(string)) };
     }
+
+    fn add_uninit<T>(&self, len: usize) -> &mut [MaybeUninit<T>] {
+        let layout = expect(Layout::array::<T>(len));
+        let data = expect(self.allocate(layout));
+
+        return unsafe { core::slice::from_raw_parts_mut(data.as_ptr() as *mut _, len) };
+    }
+
+    fn add_fill<T>(&self, len: usize, value: T) -> &mut [T]
+    where
+        T: Clone,
+    {
+        let data = self.add_uninit(len);
+        for slot in data.iter_mut() {
+            slot.write(value.clone());
+        }
+
+        return unsafe { &mut *(data as *mut [MaybeUninit<T>] as *mut [T]) };
+    }
+
+    /// Uses the iterator's size hint when it's exact, and otherwise grows the
+    /// allocation as needed.
+    fn add_iter<T, I>(&self, iter: I) -> &mut [T]
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let iter = iter.into_iter();
+
+        let mut buf = GrowBuf::for_iter(self, &iter);
+        for item in iter {
+            buf.push(item);
+        }
+
+        return buf.finish();
+    }
+
+    /// Usually called through the `alloc_format!` macro
+    fn add_fmt(&self, args: core::fmt::Arguments) -> &mut str {
+        if let Some(string) = args.as_str() {
+            return self.add_str(string);
+        }
+
+        let mut buf = GrowBuf::with_capacity(self, 64);
+        expect(core::fmt::Write::write_fmt(&mut buf, args));
+
+        return unsafe { core::str::from_utf8_unchecked_mut(buf.finish()) };
+    }
+
+    /// Panics if `bytes` contains a nul byte
+    fn add_cstr(&self, bytes: impl AsRef<[u8]>) -> &CStr {
+        let bytes = bytes.as_ref();
+        let len = bytes.len() + 1;
+
+        let data = self.add_uninit::<u8>(len);
+        for (slot, &byte) in data.iter_mut().zip(bytes) {
+            slot.write(byte);
+        }
+
+        data[len - 1].write(0);
+
+        let data = unsafe { core::slice::from_raw_parts(data.as_ptr() as *const u8, len) };
+
+        return expect(CStr::from_bytes_with_nul(data));
+    }
+
+    /// Like `new`, but the value is dropped (and its memory freed) when the
+    /// returned box goes away.
+    fn new_box<T>(&self, t: T) -> ArenaBox<'_, T>
+    where
+        Self: Sized,
+    {
+        return ArenaBox::new_in(t, self);
+    }
+
+    /// The caller has to make sure the allocator never reuses or frees the
+    /// memory while the reference is alive, e.g. by never rewinding it.
+    unsafe fn new_static<T>(&self, t: T) -> &'static mut T {
+        return &mut *(self.new(t) as *mut T);
+    }
+
+    /// Same requirements as `new_static`.
+    unsafe fn add_slice_static<T>(&self, slice: &[T]) -> &'static mut [T]
+    where
+        T: Copy,
+    {
+        return &mut *(self.add_slice(slice) as *mut [T]);
+    }
+
+    /// Same requirements as `new_static`.
+    unsafe fn add_str_static(&self, string: &str) -> &'static mut str {
+        return &mut *(self.add_str(string) as *mut str);
+    }
+}
+
+/// Writes the formatted string into an allocator, returning `&mut str`.
+///
+/// ```
+/// let bucket_list = aliu::BucketList::new();
+/// let s = aliu::alloc_format!(&bucket_list, "{}-{}", 1, 2);
+/// assert_eq!(s, "1-2");
+/// ```
+#[macro_export]
+macro_rules! alloc_format {
+    ($alloc:expr, $($arg:tt)*) => {{
+        use $crate::AllocExt as _;
+        ($alloc).add_fmt(format_args!($($arg)*))
+    }};
+}
+
+// A growable buffer inside an allocator, for building up slices whose final
+// size isn't known up front.
+pub(crate) struct GrowBuf<'a, T, A>
+where
+    A: Allocator + ?Sized,
+{
+    alloc: &'a A,
+    ptr: NonNull<T>,
+    len: usize,
+    capacity: usize,
+}
+
+impl<'a, T, A> GrowBuf<'a, T, A>
+where
+    A: Allocator + ?Sized,
+{
+    pub(crate) fn with_capacity(alloc: &'a A, capacity: usize) -> Self {
+        let capacity = match core::mem::size_of::<T>() {
+            0 => usize::MAX,
+            _ => capacity,
+        };
+
+        let layout = expect(Layout::array::<T>(capacity));
+        let ptr = expect(alloc.allocate(layout)).cast();
+
+        return Self {
+            alloc,
+            ptr,
+            len: 0,
+            capacity,
+        };
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        let needed = unwrap(self.len.checked_add(additional));
+        if needed <= self.capacity {
+            return;
+        }
+
+        let capacity = core::cmp::max(needed, self.capacity * 2);
+        let old_layout = expect(Layout::array::<T>(self.capacity));
+        let new_layout = expect(Layout::array::<T>(capacity));
+
+        let data = unsafe { self.alloc.grow(self.ptr.cast(), old_layout, new_layout) };
+
+        self.ptr = expect(data).cast();
+        self.capacity = capacity;
+    }
+
+    /// Uses the iterator's size hint when it's exact, and otherwise starts
+    /// with room for a few items.
+    pub(crate) fn for_iter<I>(alloc: &'a A, iter: &I) -> Self
+    where
+        I: Iterator<Item = T>,
+    {
+        let (lower, upper) = iter.size_hint();
+        let capacity = match upper {
+            Some(upper) if upper == lower => lower,
+            _ => core::cmp::max(lower, 8),
+        };
+
+        return Self::with_capacity(alloc, capacity);
+    }
+
+    pub(crate) fn push(&mut self, t: T) {
+        self.reserve(1);
+
+        unsafe { self.ptr.as_ptr().add(self.len).write(t) };
+        self.len += 1;
+    }
+
+    /// The items written so far. The pointer changes whenever the buffer grows
+    /// or shrinks.
+    pub(crate) fn written(&self) -> (NonNull<T>, usize) {
+        return (self.ptr, self.len);
+    }
+
+    pub(crate) fn shrink_to_fit(&mut self) {
+        if self.len < self.capacity && core::mem::size_of::<T>() != 0 {
+            let old_layout = expect(Layout::array::<T>(self.capacity));
+            let new_layout = expect(Layout::array::<T>(self.len));
+
+            let data = unsafe { self.alloc.shrink(self.ptr.cast(), old_layout, new_layout) };
+            self.ptr = expect(data).cast();
+            self.capacity = self.len;
+        }
+    }
+
+    pub(crate) fn finish(mut self) -> &'a mut [T] {
+        self.shrink_to_fit();
+
+        return unsafe { core::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) };
+    }
+}
+
+impl<'a, A> core::fmt::Write for GrowBuf<'a, u8, A>
+where
+    A: Allocator + ?Sized,
+{
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let bytes = s.as_bytes();
+        self.reserve(bytes.len());
+
+        unsafe {
+            let to = self.ptr.as_ptr().add(self.len);
+            core::ptr::copy_nonoverlapping(bytes.as_ptr(), to, bytes.len());
+        }
+
+        self.len += bytes.len();
+
+        return Ok(());
+    }
+}
+
+/// An owned value living in an allocator. Dropping it runs `T`'s destructor and
+/// hands the memory back to the allocator.
+pub struct ArenaBox<'a, T>
+where
+    T: ?Sized,
+{
+    ptr: NonNull<T>,
+    alloc: &'a dyn Allocator,
+}
+
+impl<'a, T> ArenaBox<'a, T> {
+    pub fn new_in(t: T, alloc: &'a dyn Allocator) -> Self {
+        let layout = Layout::new::<T>();
+        let data = expect(alloc.allocate(layout));
+
+        unsafe {
+            let ptr = data.cast::<T>();
+            core::ptr::write(ptr.as_ptr(), t);
+
+            return Self { ptr, alloc };
+        }
+    }
+
+    pub fn into_inner(b: Self) -> T {
+        let b = core::mem::ManuallyDrop::new(b);
+
+        unsafe {
+            let t = core::ptr::read(b.ptr.as_ptr());
+            b.alloc.deallocate(b.ptr.cast(), Layout::new::<T>());
+
+            return t;
+        }
+    }
+}
+
+impl<'a, T> ArenaBox<'a, T>
+where
+    T: ?Sized,
+{
+    /// Gives up ownership of the value without dropping it. The memory stays
+    /// allocated for as long as the allocator is borrowed.
+    pub fn leak(b: Self) -> &'a mut T {
+        let b = core::mem::ManuallyDrop::new(b);
+
+        return unsafe { &mut *b.ptr.as_ptr() };
+    }
+}
+
+impl<'a, T> Drop for ArenaBox<'a, T>
+where
+    T: ?Sized,
+{
+    fn drop(&mut self) {
+        unsafe {
+            let layout = Layout::for_value(self.ptr.as_ref());
+            core::ptr::drop_in_place(self.ptr.as_ptr());
+            self.alloc.deallocate(self.ptr.cast(), layout);
+        }
+    }
+}
+
+impl<'a, T> core::ops::Deref for ArenaBox<'a, T>
+where
+    T: ?Sized,
+{
+    type Target = T;
+
+    #[inline(always)]
+    fn deref(&self) -> &T {
+        return unsafe { self.ptr.as_ref() };
+    }
+}
+
+impl<'a, T> core::ops::DerefMut for ArenaBox<'a, T>
+where
+    T: ?Sized,
+{
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut T {
+        return unsafe { self.ptr.as_mut() };
+    }
+}
+
+impl<'a, T> core::fmt::Debug for ArenaBox<'a, T>
+where
+    T: ?Sized + core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        return core::fmt::Debug::fmt(&**self, f);
+    }
 }
@@ -0,0 +1,385 @@
+use crate::alloc_api::*;
+use crate::basic::*;
+use crate::global_bulk::*;
+use crate::pod::*;
+use alloc::alloc::Layout;
+use core::cell::Cell;
+use core::ptr::NonNull;
+
+const PAGE_SIZE: usize = 4096;
+const MIN_ORDER_SHIFT: u32 = 4;
+const MAX_ORDERS: usize = usize::BITS as usize;
+
+struct FreeNode {
+    prev: Option<NonNull<FreeNode>>,
+    next: Option<NonNull<FreeNode>>,
+}
+
+#[inline(always)]
+fn sized_slice(ptr: NonNull<u8>, size: usize) -> NonNull<[u8]> {
+    let slice = core::ptr::slice_from_raw_parts_mut(ptr.as_ptr(), size);
+
+    return unsafe { NonNull::new_unchecked(slice) };
+}
+
+/// Binary buddy allocator over a single mapped region. Every block is a power
+/// of two of at least `BuddyAllocator::MIN_BLOCK` bytes; freeing a block
+/// merges it with its buddy whenever the buddy is free too, so fragmentation
+/// stays bounded no matter how long the allocator lives.
+///
+/// Blocks are aligned to their size, up to the page size. Larger alignments
+/// fail with `AllocError`.
+pub struct BuddyAllocator {
+    base: usize,
+    size: usize,
+    max_order: usize,
+    free: [Cell<Option<NonNull<FreeNode>>>; MAX_ORDERS],
+
+    // One bit per block per order, set while the block is in a free list
+    free_bits: Cell<Pod<u64>>,
+    used: Cell<usize>,
+}
+
+impl BuddyAllocator {
+    pub const MIN_BLOCK: usize = 1 << MIN_ORDER_SHIFT;
+    pub const DEFAULT_SIZE: usize = 16 * 1024 * 1024;
+
+    pub fn new() -> Self {
+        return Self::with_capacity(Self::DEFAULT_SIZE);
+    }
+
+    /// `capacity` is rounded up to a power of two.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let size = core::cmp::max(capacity, PAGE_SIZE).next_power_of_two();
+
+        let base = match unsafe { map_region(core::ptr::null(), size) } {
+            Ok(ptr) => ptr as usize,
+            Err(e) => panic!("failed to map region"),
+        };
+
+        let max_order = (size.trailing_zeros() - MIN_ORDER_SHIFT) as usize;
+        let bit_count = 2 * (size >> MIN_ORDER_SHIFT);
+
+        let mut free_bits = Pod::new();
+        free_bits.push_repeat(0, bit_count / 64 + 1);
+
+        let buddy = Self {
+            base,
+            size,
+            max_order,
+            free: core::array::from_fn(|_| Cell::new(None)),
+            free_bits: Cell::new(free_bits),
+            used: Cell::new(0),
+        };
+
+        buddy.push_free(max_order, 0);
+
+        return buddy;
+    }
+
+    #[inline(always)]
+    fn block_size(order: usize) -> usize {
+        return Self::MIN_BLOCK << order;
+    }
+
+    #[inline(always)]
+    fn order_of(&self, layout: Layout) -> Option<usize> {
+        if layout.align() > PAGE_SIZE {
+            return None;
+        }
+
+        let size = core::cmp::max(layout.size(), layout.align());
+        let size = core::cmp::max(size, Self::MIN_BLOCK);
+        if size > self.size {
+            return None;
+        }
+
+        let shift = size.next_power_of_two().trailing_zeros();
+        return Some((shift - MIN_ORDER_SHIFT) as usize);
+    }
+
+    #[inline(always)]
+    fn bit_index(&self, order: usize, offset: usize) -> usize {
+        // Orders below this one have 2n - 2n / 2^order blocks in total
+        let double_count = 2 * (self.size >> MIN_ORDER_SHIFT);
+        let level_start = double_count - (double_count >> order);
+
+        return level_start + (offset >> (order as u32 + MIN_ORDER_SHIFT));
+    }
+
+    fn is_free(&self, order: usize, offset: usize) -> bool {
+        let index = self.bit_index(order, offset);
+
+        let bits = self.free_bits.replace(Pod::new());
+        let is_free = bits[index / 64] & (1 << (index % 64)) != 0;
+        self.free_bits.replace(bits);
+
+        return is_free;
+    }
+
+    fn set_free(&self, order: usize, offset: usize, free: bool) {
+        let index = self.bit_index(order, offset);
+
+        let mut bits = self.free_bits.replace(Pod::new());
+        match free {
+            true => bits[index / 64] |= 1 << (index % 64),
+            false => bits[index / 64] &= !(1 << (index % 64)),
+        }
+
+        self.free_bits.replace(bits);
+    }
+
+    #[inline(always)]
+    fn node(&self, offset: usize) -> NonNull<FreeNode> {
+        return unsafe { NonNull::new_unchecked((self.base + offset) as *mut FreeNode) };
+    }
+
+    fn push_free(&self, order: usize, offset: usize) {
+        let mut node = self.node(offset);
+        let head = self.free[order].get();
+
+        unsafe {
+            *node.as_mut() = FreeNode {
+                prev: None,
+                next: head,
+            };
+
+            if let Some(mut head) = head {
+                head.as_mut().prev = Some(node);
+            }
+        }
+
+        self.free[order].set(Some(node));
+        self.set_free(order, offset, true);
+    }
+
+    fn remove_free(&self, order: usize, offset: usize) {
+        let node = self.node(offset);
+
+        unsafe {
+            let FreeNode { prev, next } = node.as_ptr().read();
+
+            match prev {
+                Some(mut prev) => prev.as_mut().next = next,
+                None => self.free[order].set(next),
+            }
+
+            if let Some(mut next) = next {
+                next.as_mut().prev = prev;
+            }
+        }
+
+        self.set_free(order, offset, false);
+    }
+
+    fn alloc_block(&self, order: usize) -> Option<usize> {
+        let mut found = order;
+        while self.free[found].get().is_none() {
+            if found == self.max_order {
+                return None;
+            }
+
+            found += 1;
+        }
+
+        let node = self.free[found].get()?;
+        let offset = node.as_ptr() as usize - self.base;
+        self.remove_free(found, offset);
+
+        while found > order {
+            found -= 1;
+            self.push_free(found, offset + Self::block_size(found));
+        }
+
+        self.used.set(self.used.get() + Self::block_size(order));
+
+        return Some(offset);
+    }
+
+    fn free_block(&self, mut order: usize, mut offset: usize) {
+        self.used.set(self.used.get() - Self::block_size(order));
+
+        while order < self.max_order {
+            let buddy = offset ^ Self::block_size(order);
+            if !self.is_free(order, buddy) {
+                break;
+            }
+
+            self.remove_free(order, buddy);
+            offset = core::cmp::min(offset, buddy);
+            order += 1;
+        }
+
+        self.push_free(order, offset);
+    }
+
+    // Grows a block by absorbing the buddies to its right, if they're all
+    // free.
+    fn grow_block(&self, offset: usize, old_order: usize, new_order: usize) -> bool {
+        for order in old_order..new_order {
+            let size = Self::block_size(order);
+            let is_left = offset & size == 0;
+
+            if !is_left || !self.is_free(order, offset + size) {
+                return false;
+            }
+        }
+
+        for order in old_order..new_order {
+            self.remove_free(order, offset + Self::block_size(order));
+        }
+
+        let grown_by = Self::block_size(new_order) - Self::block_size(old_order);
+        self.used.set(self.used.get() + grown_by);
+
+        return true;
+    }
+
+    fn shrink_block(&self, offset: usize, old_order: usize, new_order: usize) {
+        for order in (new_order..old_order).rev() {
+            self.push_free(order, offset + Self::block_size(order));
+        }
+
+        let shrunk_by = Self::block_size(old_order) - Self::block_size(new_order);
+        self.used.set(self.used.get() - shrunk_by);
+    }
+
+    unsafe fn resize(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+        zeroed: bool,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let (old_size, new_size) = (old_layout.size(), new_layout.size());
+        let old_order = unwrap(self.order_of(old_layout));
+        let new_order = self.order_of(new_layout).ok_or(AllocError)?;
+        let offset = ptr.as_ptr() as usize - self.base;
+
+        let in_place = match new_order.cmp(&old_order) {
+            core::cmp::Ordering::Greater => self.grow_block(offset, old_order, new_order),
+            core::cmp::Ordering::Less => {
+                self.shrink_block(offset, old_order, new_order);
+                true
+            }
+            core::cmp::Ordering::Equal => true,
+        };
+
+        if in_place {
+            if zeroed && new_size > old_size {
+                let tail = ptr.as_ptr().add(old_size);
+                tail.write_bytes(0, new_size - old_size);
+            }
+
+            return Ok(sized_slice(ptr, new_size));
+        }
+
+        let new_ptr = match zeroed {
+            true => self.allocate_zeroed(new_layout)?,
+            false => self.allocate(new_layout)?,
+        };
+
+        core::ptr::copy_nonoverlapping(ptr.as_ptr(), new_ptr.as_ptr() as *mut u8, old_size);
+        self.deallocate(ptr, old_layout);
+
+        return Ok(new_ptr);
+    }
+}
+
+impl Drop for BuddyAllocator {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = unmap_region(self.base as *const (), self.size);
+        }
+    }
+}
+
+unsafe impl Allocator for BuddyAllocator {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let order = self.order_of(layout).ok_or(AllocError)?;
+        let offset = self.alloc_block(order).ok_or(AllocError)?;
+        let ptr = unsafe { NonNull::new_unchecked((self.base + offset) as *mut u8) };
+
+        return Ok(sized_slice(ptr, layout.size()));
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        let order = unwrap(self.order_of(layout));
+        let offset = ptr.as_ptr() as usize - self.base;
+
+        self.free_block(order, offset);
+    }
+
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return self.resize(ptr, old_layout, new_layout, false);
+    }
+
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return self.resize(ptr, old_layout, new_layout, true);
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return self.resize(ptr, old_layout, new_layout, false);
+    }
+
+    fn owns(&self, ptr: NonNull<u8>) -> bool {
+        let addr = ptr.as_ptr() as usize;
+
+        return self.base <= addr && addr < self.base + self.size;
+    }
+}
+
+impl AllocStat for BuddyAllocator {
+    fn total_used(&self) -> usize {
+        return self.used.get();
+    }
+
+    fn total_capacity(&self) -> usize {
+        return self.size;
+    }
+}
+
+/// Dumps the free lists, one line per non-empty order, as offsets from the
+/// start of the region.
+impl core::fmt::Debug for BuddyAllocator {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        writeln!(
+            f,
+            "BuddyAllocator ({} / {} bytes used)",
+            self.used.get(),
+            self.size
+        )?;
+
+        for order in 0..=self.max_order {
+            let mut node = self.free[order].get();
+            if node.is_none() {
+                continue;
+            }
+
+            write!(f, "  {:>10}:", Self::block_size(order))?;
+            while let Some(n) = node {
+                write!(f, " {:#x}", n.as_ptr() as usize - self.base)?;
+                node = unsafe { n.as_ref().next };
+            }
+
+            writeln!(f)?;
+        }
+
+        return Ok(());
+    }
+}
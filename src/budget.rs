@@ -0,0 +1,157 @@
+use crate::alloc_api::*;
+use alloc::alloc::Layout;
+use core::ptr::NonNull;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Forwards to `A`, but fails any request that would bring the number of live
+/// bytes above a limit. The count is atomic, so unlike
+/// `FailingAllocator::with_budget` this can be shared between threads, e.g. as
+/// a `global_allocator!`.
+///
+/// `shrink` and `deallocate` always go through, and give their bytes back to
+/// the budget.
+pub struct Budgeted<A>
+where
+    A: Allocator,
+{
+    inner: A,
+    limit: usize,
+    live_bytes: AtomicUsize,
+}
+
+impl<A> Budgeted<A>
+where
+    A: Allocator,
+{
+    pub const fn new(inner: A, limit: usize) -> Self {
+        return Self {
+            inner,
+            limit,
+            live_bytes: AtomicUsize::new(0),
+        };
+    }
+
+    #[inline(always)]
+    pub fn inner(&self) -> &A {
+        return &self.inner;
+    }
+
+    #[inline(always)]
+    pub fn limit(&self) -> usize {
+        return self.limit;
+    }
+
+    #[inline(always)]
+    pub fn live_bytes(&self) -> usize {
+        return self.live_bytes.load(Ordering::Relaxed);
+    }
+
+    #[inline(always)]
+    pub fn remaining(&self) -> usize {
+        return self.limit - self.live_bytes();
+    }
+
+    fn take(&self, bytes: usize) -> Result<(), AllocError> {
+        let relaxed = Ordering::Relaxed;
+        let result = self.live_bytes.fetch_update(relaxed, relaxed, |live| {
+            return live.checked_add(bytes).filter(|&total| total <= self.limit);
+        });
+
+        return result.map(|_| ()).map_err(|_| AllocError);
+    }
+
+    fn give_back(&self, bytes: usize) {
+        self.live_bytes.fetch_sub(bytes, Ordering::Relaxed);
+    }
+
+    // Runs `f` with `bytes` taken out of the budget, and puts them back if it
+    // fails
+    fn with_bytes(
+        &self,
+        bytes: usize,
+        f: impl FnOnce() -> Result<NonNull<[u8]>, AllocError>,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.take(bytes)?;
+
+        let result = f();
+        if result.is_err() {
+            self.give_back(bytes);
+        }
+
+        return result;
+    }
+}
+
+unsafe impl<A> Allocator for Budgeted<A>
+where
+    A: Allocator,
+{
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        return self.with_bytes(layout.size(), || self.inner.allocate(layout));
+    }
+
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        return self.with_bytes(layout.size(), || self.inner.allocate_zeroed(layout));
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.inner.deallocate(ptr, layout);
+        self.give_back(layout.size());
+    }
+
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let grown_by = new_layout.size() - old_layout.size();
+
+        return self.with_bytes(grown_by, || self.inner.grow(ptr, old_layout, new_layout));
+    }
+
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let grown_by = new_layout.size() - old_layout.size();
+
+        return self.with_bytes(grown_by, || {
+            return self.inner.grow_zeroed(ptr, old_layout, new_layout);
+        });
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let result = self.inner.shrink(ptr, old_layout, new_layout);
+        if result.is_ok() {
+            self.give_back(old_layout.size() - new_layout.size());
+        }
+
+        return result;
+    }
+
+    #[inline]
+    fn owns(&self, ptr: NonNull<u8>) -> bool {
+        return self.inner.owns(ptr);
+    }
+}
+
+impl<A> AllocStat for Budgeted<A>
+where
+    A: AllocStat,
+{
+    fn total_used(&self) -> usize {
+        return self.inner.total_used();
+    }
+
+    fn total_capacity(&self) -> usize {
+        return self.inner.total_capacity();
+    }
+}
@@ -1,10 +1,11 @@
 use super::alloc_api::*;
 use super::pod::*;
+use super::stack::sized_slice;
 use super::unwrap;
-use alloc::alloc::{alloc, dealloc, Layout};
+use alloc::alloc::Layout;
 use core::cell::Cell;
-use std::ptr::NonNull;
-use std::{cmp, mem, ptr, slice, str};
+use core::ptr::NonNull;
+use core::{cmp, mem, ptr, slice, str};
 
 #[derive(Clone, Copy)]
 struct Bump {
@@ -22,15 +23,14 @@ const EMPTY_BUMP: Bump = Bump {
 };
 
 impl Bump {
-    fn new(layout: Layout) -> Bump {
-        let ptr = unsafe { alloc(layout) };
-        let ptr = unwrap(NonNull::new(ptr));
+    fn new(allocator: &impl Allocator, layout: Layout) -> Option<Bump> {
+        let ptr = allocator.allocate(layout).ok()?.cast::<u8>();
 
-        return Bump {
+        return Some(Bump {
             ptr,
             current: ptr,
             layout,
-        };
+        });
     }
 
     fn alloc(&mut self, layout: Layout) -> Option<*mut u8> {
@@ -38,10 +38,6 @@ impl Bump {
             return None;
         }
 
-        if layout.align() > 8 {
-            panic!("Not handled");
-        }
-
         let required_offset = self.current.as_ptr().align_offset(layout.align());
         if required_offset == usize::MAX {
             return None;
@@ -61,11 +57,128 @@ impl Bump {
             return None;
         }
     }
+
+    // Moves `current` if the block at `ptr` is the last one in this bump and
+    // its new size still fits
+    fn resize_last(&mut self, ptr: NonNull<u8>, old_size: usize, new_layout: Layout) -> bool {
+        let addr = ptr.as_ptr() as usize;
+        let begin = self.ptr.as_ptr() as usize;
+        let bump_end = begin + self.layout.size();
+
+        if addr < begin || addr + old_size != self.current.as_ptr() as usize {
+            return false;
+        }
+
+        if !addr.is_multiple_of(new_layout.align()) {
+            return false;
+        }
+
+        let new_end = match addr.checked_add(new_layout.size()) {
+            Some(end) if end <= bump_end => end,
+            _ => return false,
+        };
+
+        self.current = unsafe { NonNull::new_unchecked(new_end as *mut u8) };
+
+        return true;
+    }
 }
 
-pub struct BucketList {
+const BUCKET_ALIGN: usize = 8;
+
+/// Bump allocator that grabs memory from `A` in buckets, and gives it back all
+/// at once when it's dropped or rewound.
+pub struct BucketList<A = Global>
+where
+    A: Allocator,
+{
     allocations: Cell<Pod<Bump>>,
     index: Cell<usize>,
+    policy: BucketListBuilder,
+    next_size: Cell<usize>,
+    allocator: A,
+}
+
+/// Settings for how a `BucketList` sizes its buckets. The defaults match
+/// `BucketList::new()`: every bucket is `BucketList::DEFAULT_BUCKET_SIZE`
+/// bytes, unless an allocation needs more, and there's no limit.
+#[derive(Clone, Copy)]
+pub struct BucketListBuilder {
+    initial_size: usize,
+    growth_factor: usize,
+    max_bucket_size: usize,
+    memory_limit: Option<usize>,
+    zeroed: bool,
+}
+
+impl BucketListBuilder {
+    pub fn new() -> Self {
+        return Self {
+            initial_size: BucketList::DEFAULT_BUCKET_SIZE,
+            growth_factor: 1,
+            max_bucket_size: usize::MAX,
+            memory_limit: None,
+            zeroed: false,
+        };
+    }
+
+    /// Size of the first bucket. It's clamped to `max_bucket_size`, and a
+    /// size of 0 is treated as 1.
+    pub fn initial_size(mut self, size: usize) -> Self {
+        self.initial_size = size;
+        return self;
+    }
+
+    /// Each new bucket is this many times bigger than the last one, up to
+    /// `max_bucket_size`. A factor of 1 keeps them all the same size, and 0 is
+    /// treated as 1.
+    pub fn growth_factor(mut self, factor: usize) -> Self {
+        self.growth_factor = cmp::max(factor, 1);
+        return self;
+    }
+
+    /// Caps the growth of bucket sizes, including the first bucket. Allocations
+    /// bigger than this still get a bucket of their own.
+    pub fn max_bucket_size(mut self, size: usize) -> Self {
+        self.max_bucket_size = size;
+        return self;
+    }
+
+    /// Hard limit on the total size of all buckets. Allocations that would go
+    /// over it fail with `AllocError`.
+    pub fn memory_limit(mut self, bytes: usize) -> Self {
+        self.memory_limit = Some(bytes);
+        return self;
+    }
+
+    /// Hands out zeroed memory for every allocation, including memory that's
+    /// reused after a rewind.
+    pub fn zeroed(mut self, zeroed: bool) -> Self {
+        self.zeroed = zeroed;
+        return self;
+    }
+
+    pub fn build(self) -> BucketList {
+        return self.build_with_allocator(Global);
+    }
+
+    pub fn build_with_allocator<A>(self, allocator: A) -> BucketList<A>
+    where
+        A: Allocator,
+    {
+        // A zero size would leave no room for zero-size allocations once the
+        // current bucket is full
+        let initial_size = cmp::min(self.initial_size, self.max_bucket_size);
+        let initial_size = cmp::max(initial_size, 1);
+
+        return BucketList {
+            allocations: Cell::new(Pod::new()),
+            index: Cell::new(0),
+            policy: self,
+            next_size: Cell::new(initial_size),
+            allocator,
+        };
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -74,33 +187,95 @@ pub struct BucketListMark {
     current: NonNull<u8>,
 }
 
-impl BucketList {
+impl BucketList<Global> {
     pub const DEFAULT_BUCKET_SIZE: usize = 2 * 1024 * 1024;
 
     #[inline(always)]
     pub fn new() -> Self {
-        return Self {
-            allocations: Cell::new(Pod::new()),
-            index: Cell::new(0),
-        };
+        return BucketListBuilder::new().build();
+    }
+
+    #[inline(always)]
+    pub fn builder() -> BucketListBuilder {
+        return BucketListBuilder::new();
     }
 
     pub fn with_capacity(capacity: usize) -> Self {
-        let layout = match Layout::from_size_align(capacity, 8) {
+        let layout = match Layout::from_size_align(capacity, BUCKET_ALIGN) {
             Ok(layout) => layout,
             Err(e) => panic!("failed to make Layout"),
         };
 
         let mut allocations = Pod::new();
 
-        let bump = Bump::new(layout);
+        let bump = unwrap(Bump::new(&Global, layout));
 
         allocations.push(bump);
 
-        return Self {
-            allocations: Cell::new(allocations),
-            index: Cell::new(0),
+        let list = BucketListBuilder::new().build();
+        list.allocations.replace(allocations);
+
+        return list;
+    }
+}
+
+impl<A> BucketList<A>
+where
+    A: Allocator,
+{
+    /// Buckets come from `allocator`, e.g. another arena or a `Tracked`
+    /// wrapper, instead of the global heap.
+    pub fn with_allocator(allocator: A) -> Self {
+        return BucketListBuilder::new().build_with_allocator(allocator);
+    }
+
+    #[inline(always)]
+    pub fn allocator(&self) -> &A {
+        return &self.allocator;
+    }
+
+    // Buckets are only guaranteed to be aligned to `BUCKET_ALIGN`, so a fresh
+    // bucket needs room for the worst-case padding in front of the allocation
+    fn new_bucket(&self, allocations: &Pod<Bump>, layout: Layout) -> Option<Bump> {
+        let padding = layout.align() - cmp::min(layout.align(), BUCKET_ALIGN);
+        let needed = layout.size().checked_add(padding)?;
+        let mut size = cmp::max(needed, self.next_size.get());
+
+        if let Some(limit) = self.policy.memory_limit {
+            let capacity: usize = allocations.iter().map(|b| b.layout.size()).sum();
+            let left = limit.saturating_sub(capacity);
+            if needed > left {
+                return None;
+            }
+
+            size = cmp::min(size, left);
+        }
+
+        if size == 0 {
+            return None;
+        }
+
+        let layout = Layout::from_size_align(size, BUCKET_ALIGN).ok()?;
+        let bump = match Bump::new(&self.allocator, layout) {
+            Some(bump) => bump,
+
+            // The backing allocator might still have room for a bucket that
+            // only fits this allocation
+            None if size > needed && needed > 0 => {
+                let layout = Layout::from_size_align(needed, BUCKET_ALIGN).ok()?;
+                Bump::new(&self.allocator, layout)?
+            }
+            None => return None,
         };
+
+        let next_size = self
+            .next_size
+            .get()
+            .saturating_mul(self.policy.growth_factor);
+        let next_size = cmp::min(next_size, self.policy.max_bucket_size);
+        self.next_size.set(cmp::max(next_size, 1));
+
+        return Some(bump);
     }
 
     pub fn save(&self) -> BucketListMark {
@@ -129,10 +304,17 @@ impl BucketList {
 
         let bump = match allocations.get_mut(mark.index) {
             Some(b) => b,
-            None => return,
+            None => {
+                self.allocations.replace(allocations);
+                return;
+            }
         };
 
-        bump.current = bump.ptr;
+        // The mark is dangling if the list was empty when it was made
+        bump.current = match mark.current == DANGLING {
+            true => bump.ptr,
+            false => mark.current,
+        };
 
         for bump in &mut allocations[(mark.index + 1)..(self.index.get() + 1)] {
             bump.current = bump.ptr;
@@ -142,64 +324,217 @@ impl BucketList {
         self.allocations.replace(allocations);
     }
 
-    pub fn scoped<'a>(&'a mut self) -> ScopedBump<'a> {
+    /// Rewinds to the very beginning, keeping every bucket around for reuse.
+    pub fn reset(&mut self) {
+        let mut allocations = self.allocations.replace(Pod::new());
+
+        for bump in allocations.iter_mut() {
+            bump.current = bump.ptr;
+        }
+
+        self.index.set(0);
+        self.allocations.replace(allocations);
+    }
+
+    /// Frees the empty buckets past the current one, except for the first few
+    /// of them that add up to at most `keep_bytes`.
+    pub fn release_unused(&self, keep_bytes: usize) {
+        let mut allocations = self.allocations.replace(Pod::new());
+
+        let mut kept = 0;
+        let mut len = cmp::min(self.index.get() + 1, allocations.len());
+        for bump in &allocations[len..] {
+            if kept + bump.layout.size() > keep_bytes {
+                break;
+            }
+
+            kept += bump.layout.size();
+            len += 1;
+        }
+
+        for bump in &allocations[len..] {
+            unsafe { self.allocator.deallocate(bump.ptr, bump.layout) };
+        }
+
+        allocations.truncate(len);
+        self.allocations.replace(allocations);
+    }
+
+    pub fn scoped<'a>(&'a mut self) -> ScopedBump<'a, A> {
         let mark = self.save();
 
         return ScopedBump { mark, alloc: self };
     }
+
+    // The last allocation in the current bucket is resized by moving the bump
+    // pointer, if `in_place` allows it. Anything else that shrinks keeps its
+    // address, and anything else that grows is copied to a new block.
+    unsafe fn resize(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+        zeroed: bool,
+        in_place: bool,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let (old_size, new_size) = (old_layout.size(), new_layout.size());
+
+        let mut allocations = self.allocations.replace(Pod::new());
+        let resized = match allocations.get_mut(self.index.get()) {
+            Some(bump) if in_place => bump.resize_last(ptr, old_size, new_layout),
+            _ => false,
+        };
+
+        self.allocations.replace(allocations);
+
+        if resized {
+            if (zeroed || self.policy.zeroed) && new_size > old_size {
+                let tail = ptr.as_ptr().add(old_size);
+                tail.write_bytes(0, new_size - old_size);
+            }
+
+            return Ok(sized_slice(ptr, new_size));
+        }
+
+        let aligned = (ptr.as_ptr() as usize).is_multiple_of(new_layout.align());
+        if new_size <= old_size && aligned {
+            return Ok(sized_slice(ptr, new_size));
+        }
+
+        let new_ptr = match zeroed {
+            true => self.allocate_zeroed(new_layout)?,
+            false => self.allocate(new_layout)?,
+        };
+
+        let copied = cmp::min(old_size, new_size);
+        ptr::copy_nonoverlapping(ptr.as_ptr(), new_ptr.as_ptr() as *mut u8, copied);
+
+        return Ok(new_ptr);
+    }
 }
 
-unsafe impl Send for BucketList {}
+unsafe impl<A> Send for BucketList<A> where A: Allocator + Send {}
 
-impl Drop for BucketList {
+impl<A> Drop for BucketList<A>
+where
+    A: Allocator,
+{
     fn drop(&mut self) {
         let allocations = self.allocations.replace(Pod::new());
 
         for bump in allocations {
             unsafe {
-                dealloc(bump.ptr.as_ptr(), bump.layout);
+                self.allocator.deallocate(bump.ptr, bump.layout);
             }
         }
     }
 }
 
-unsafe impl Allocator for BucketList {
+unsafe impl<A> Allocator for BucketList<A>
+where
+    A: Allocator,
+{
     fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
         let mut index = self.index.get();
         let mut allocations = self.allocations.replace(Pod::new());
 
-        let size = std::cmp::max(layout.size(), Self::DEFAULT_BUCKET_SIZE);
-        let bump_layout = unsafe { Layout::from_size_align_unchecked(size, layout.align()) };
+        let mut found = allocations.get_mut(index).and_then(|b| b.alloc(layout));
 
-        if allocations.len() == 0 {
-            let bump = Bump::new(bump_layout);
-            allocations.push(bump);
+        // Buckets past `index` are empty, either left over from a rewind or
+        // kept by `release_unused`, so they get reused in order. Ones that are
+        // too small are skipped until the next rewind.
+        while found.is_none() && index + 1 < allocations.len() {
+            index += 1;
+            found = allocations[index].alloc(layout);
         }
 
-        let ptr = allocations[index].alloc(layout).unwrap_or_else(|| {
-            let mut bump = Bump::new(bump_layout);
-            let ptr = unwrap(bump.alloc(layout));
-            index += 1;
+        if found.is_none() {
+            let mut bump = match self.new_bucket(&allocations, layout) {
+                Some(bump) => bump,
+                None => {
+                    self.allocations.replace(allocations);
+                    return Err(AllocError);
+                }
+            };
+
+            found = bump.alloc(layout);
+            index = allocations.len();
 
             allocations.push(bump);
+        }
 
-            return ptr;
-        });
+        self.allocations.replace(allocations);
+        self.index.set(index);
+
+        let ptr = unwrap(found);
+        if self.policy.zeroed {
+            unsafe { ptr.write_bytes(0, layout.size()) };
+        }
 
         let slice = unsafe { core::slice::from_raw_parts_mut(ptr, layout.size()) };
-        let ptr = NonNull::new(slice).ok_or(AllocError)?;
+        return NonNull::new(slice).ok_or(AllocError);
+    }
 
-        self.allocations.replace(allocations);
-        self.index.set(index);
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let ptr = self.allocate(layout)?;
+        if !self.policy.zeroed {
+            unsafe { (ptr.as_ptr() as *mut u8).write_bytes(0, layout.size()) };
+        }
 
         return Ok(ptr);
     }
 
     // deallocation doesn't do anything
     unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {}
+
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return self.resize(ptr, old_layout, new_layout, false, true);
+    }
+
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return self.resize(ptr, old_layout, new_layout, true, true);
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return self.resize(ptr, old_layout, new_layout, false, true);
+    }
+
+    fn owns(&self, ptr: NonNull<u8>) -> bool {
+        let allocations = self.allocations.replace(Pod::new());
+
+        let addr = ptr.as_ptr() as usize;
+        let owns = allocations.iter().any(|bump| {
+            let begin = bump.ptr.as_ptr() as usize;
+            let current = bump.current.as_ptr() as usize;
+
+            begin <= addr && addr < current
+        });
+
+        self.allocations.replace(allocations);
+
+        return owns;
+    }
 }
 
-impl AllocStat for BucketList {
+impl<A> AllocStat for BucketList<A>
+where
+    A: Allocator,
+{
     fn total_used(&self) -> usize {
         let allocations = self.allocations.replace(Pod::new());
 
@@ -231,13 +566,19 @@ impl AllocStat for BucketList {
     }
 }
 
-pub struct ScopedBump<'a> {
+pub struct ScopedBump<'a, A = Global>
+where
+    A: Allocator,
+{
     mark: BucketListMark,
-    alloc: &'a mut BucketList,
+    alloc: &'a mut BucketList<A>,
 }
 
-impl<'a> ScopedBump<'a> {
-    pub fn chain<'b>(&'b mut self) -> ScopedBump<'b> {
+impl<'a, A> ScopedBump<'a, A>
+where
+    A: Allocator,
+{
+    pub fn chain<'b>(&'b mut self) -> ScopedBump<'b, A> {
         let mark = self.alloc.save();
 
         return ScopedBump {
@@ -245,43 +586,114 @@ impl<'a> ScopedBump<'a> {
             alloc: &mut self.alloc,
         };
     }
+
+    // Blocks from before the mark can't move the bump pointer, since
+    // rewinding to the mark would cut them short
+    fn can_resize_in_place(&self, ptr: NonNull<u8>) -> bool {
+        if self.alloc.index.get() > self.mark.index {
+            return true;
+        }
+
+        return ptr.as_ptr() >= self.mark.current.as_ptr();
+    }
 }
 
-impl<'a> Drop for ScopedBump<'a> {
+impl<'a, A> Drop for ScopedBump<'a, A>
+where
+    A: Allocator,
+{
     fn drop(&mut self) {
         unsafe {
             self.alloc.set(self.mark);
         }
     }
 }
-unsafe impl<'a> Allocator for ScopedBump<'a> {
+unsafe impl<'a, A> Allocator for ScopedBump<'a, A>
+where
+    A: Allocator,
+{
     fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
         return self.alloc.allocate(layout);
     }
 
     // deallocation doesn't do anything
     unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {}
+
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return self.alloc.resize(
+            ptr,
+            old_layout,
+            new_layout,
+            false,
+            self.can_resize_in_place(ptr),
+        );
+    }
+
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return self.alloc.resize(
+            ptr,
+            old_layout,
+            new_layout,
+            true,
+            self.can_resize_in_place(ptr),
+        );
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return self.alloc.resize(
+            ptr,
+            old_layout,
+            new_layout,
+            false,
+            self.can_resize_in_place(ptr),
+        );
+    }
+
+    fn owns(&self, ptr: NonNull<u8>) -> bool {
+        return self.alloc.owns(ptr);
+    }
 }
 
-impl AllocStat for ScopedBump<'_> {
+impl<A> AllocStat for ScopedBump<'_, A>
+where
+    A: Allocator,
+{
     fn total_used(&self) -> usize {
         let allocations = self.alloc.allocations.replace(Pod::new());
 
         let mut used = 0;
 
         if allocations.len() != 0 {
-            for bump in &allocations[(self.mark.index + 1)..] {
+            for bump in &allocations[self.mark.index..] {
                 let current = bump.current.as_ptr() as usize;
                 let begin = bump.ptr.as_ptr() as usize;
 
                 used += current - begin;
             }
 
-            let bump = allocations[self.mark.index];
-            let current = self.mark.current.as_ptr() as usize;
-            let begin = bump.ptr.as_ptr() as usize;
+            // The mark is dangling if the list was empty when it was made
+            if self.mark.current != DANGLING {
+                let bump = allocations[self.mark.index];
+                let current = self.mark.current.as_ptr() as usize;
+                let begin = bump.ptr.as_ptr() as usize;
 
-            used -= current - begin;
+                used -= current - begin;
+            }
         }
 
         self.alloc.allocations.replace(allocations);
@@ -295,15 +707,17 @@ impl AllocStat for ScopedBump<'_> {
         let mut capacity = 0;
 
         if allocations.len() != 0 {
-            for bump in &allocations[(self.mark.index + 1)..] {
+            for bump in &allocations[self.mark.index..] {
                 capacity += bump.layout.size();
             }
 
-            let bump = allocations[self.mark.index];
-            let current = self.mark.current.as_ptr() as usize;
-            let begin = bump.ptr.as_ptr() as usize;
+            if self.mark.current != DANGLING {
+                let bump = allocations[self.mark.index];
+                let current = self.mark.current.as_ptr() as usize;
+                let begin = bump.ptr.as_ptr() as usize;
 
-            capacity -= current - begin;
+                capacity -= current - begin;
+            }
         }
 
         self.alloc.allocations.replace(allocations);
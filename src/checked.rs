@@ -0,0 +1,289 @@
+use crate::alloc_api::*;
+use crate::pod::*;
+use alloc::alloc::Layout;
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::string::String;
+use core::cell::{Cell, RefCell};
+use core::fmt::Write;
+use core::ptr::NonNull;
+
+// How many freed blocks are remembered for double free reports
+const FREED_LIMIT: usize = 1024;
+
+#[derive(Default)]
+struct Records {
+    live: BTreeMap<usize, Layout>,
+    freed: BTreeMap<usize, Layout>,
+
+    // Addresses in `freed`, oldest first. An address that was handed out again
+    // can still be in here, which at worst forgets a later free a bit early.
+    free_order: VecDeque<usize>,
+}
+
+/// Wraps an allocator and keeps a record of every block it hands out, so that
+/// double frees, frees of unknown pointers, and layout mismatches panic right
+/// away instead of corrupting memory. Dropping it with blocks still live
+/// panics with a list of the leaks.
+///
+/// Zero-size blocks aren't tracked, since allocators are free to hand out the
+/// same pointer for all of them. Only the most recent frees are remembered, so
+/// a double free of a block freed long ago is reported as a free of a pointer
+/// that was never allocated.
+///
+/// The reports go through `core::panic!` rather than the crate's `panic!`, so
+/// release builds still print them instead of just crashing.
+pub struct CheckedAllocator<A>
+where
+    A: Allocator,
+{
+    inner: A,
+    records: RefCell<Records>,
+
+    // Set once a check has failed, so the leak report doesn't panic again
+    // while unwinding (or abort, without std)
+    failed: Cell<bool>,
+}
+
+impl<A> CheckedAllocator<A>
+where
+    A: Allocator,
+{
+    pub fn new(inner: A) -> Self {
+        return Self {
+            inner,
+            records: RefCell::new(Records::default()),
+            failed: Cell::new(false),
+        };
+    }
+
+    #[inline(always)]
+    pub fn inner(&self) -> &A {
+        return &self.inner;
+    }
+
+    pub fn live_count(&self) -> usize {
+        return self.records.borrow().live.len();
+    }
+
+    pub fn leaks(&self) -> Pod<(NonNull<u8>, Layout)> {
+        let records = self.records.borrow();
+        let mut leaks = Pod::with_capacity(records.live.len());
+
+        for (&ptr, &layout) in records.live.iter() {
+            let ptr = unsafe { NonNull::new_unchecked(ptr as *mut u8) };
+            leaks.push((ptr, layout));
+        }
+
+        return leaks;
+    }
+
+    /// Forgets about every live block, e.g. after the memory was reclaimed
+    /// some other way.
+    pub fn forget_all(&self) {
+        let mut records = self.records.borrow_mut();
+        records.live.clear();
+        records.freed.clear();
+        records.free_order.clear();
+    }
+
+    fn record(&self, ptr: NonNull<[u8]>, layout: Layout) {
+        if layout.size() == 0 {
+            return;
+        }
+
+        let addr = ptr.as_ptr() as *mut u8 as usize;
+        let mut records = self.records.borrow_mut();
+        records.freed.remove(&addr);
+
+        if let Some(prev) = records.live.insert(addr, layout) {
+            self.failed.set(true);
+            core::panic!(
+                "CheckedAllocator: allocator returned {:#x} for {:?}, but it's still live with {:?}",
+                addr, layout, prev
+            );
+        }
+    }
+
+    fn check(&self, ptr: NonNull<u8>, layout: Layout, action: &str) {
+        if layout.size() == 0 {
+            return;
+        }
+
+        let addr = ptr.as_ptr() as usize;
+        let records = self.records.borrow();
+
+        let recorded = match records.live.get(&addr) {
+            Some(&recorded) => recorded,
+            None => {
+                self.failed.set(true);
+                if let Some(prev) = records.freed.get(&addr) {
+                    core::panic!(
+                        "CheckedAllocator: {} of {:#x}, which was already freed (double free of {:?})",
+                        action, addr, prev
+                    );
+                }
+
+                core::panic!(
+                    "CheckedAllocator: {} of {:#x}, which was never allocated",
+                    action,
+                    addr
+                );
+            }
+        };
+
+        if recorded != layout {
+            self.failed.set(true);
+            core::panic!(
+                "CheckedAllocator: layout mismatch in {} of {:#x}: allocated with {:?}, but got {:?}",
+                action, addr, recorded, layout
+            );
+        }
+    }
+
+    fn release(&self, ptr: NonNull<u8>, layout: Layout) {
+        if layout.size() == 0 {
+            return;
+        }
+
+        let addr = ptr.as_ptr() as usize;
+        let mut records = self.records.borrow_mut();
+        records.live.remove(&addr);
+        records.freed.insert(addr, layout);
+        records.free_order.push_back(addr);
+
+        if records.free_order.len() > FREED_LIMIT {
+            if let Some(oldest) = records.free_order.pop_front() {
+                records.freed.remove(&oldest);
+            }
+        }
+    }
+}
+
+unsafe impl<A> Allocator for CheckedAllocator<A>
+where
+    A: Allocator,
+{
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let ptr = self.inner.allocate(layout)?;
+        self.record(ptr, layout);
+
+        return Ok(ptr);
+    }
+
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let ptr = self.inner.allocate_zeroed(layout)?;
+        self.record(ptr, layout);
+
+        return Ok(ptr);
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.check(ptr, layout, "deallocate");
+        self.release(ptr, layout);
+        self.inner.deallocate(ptr, layout);
+    }
+
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.check(ptr, old_layout, "grow");
+
+        let new_ptr = self.inner.grow(ptr, old_layout, new_layout)?;
+        self.release(ptr, old_layout);
+        self.record(new_ptr, new_layout);
+
+        return Ok(new_ptr);
+    }
+
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.check(ptr, old_layout, "grow_zeroed");
+
+        let new_ptr = self.inner.grow_zeroed(ptr, old_layout, new_layout)?;
+        self.release(ptr, old_layout);
+        self.record(new_ptr, new_layout);
+
+        return Ok(new_ptr);
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.check(ptr, old_layout, "shrink");
+
+        let new_ptr = self.inner.shrink(ptr, old_layout, new_layout)?;
+        self.release(ptr, old_layout);
+        self.record(new_ptr, new_layout);
+
+        return Ok(new_ptr);
+    }
+
+    fn owns(&self, ptr: NonNull<u8>) -> bool {
+        let addr = ptr.as_ptr() as usize;
+        if self.records.borrow().live.contains_key(&addr) {
+            return true;
+        }
+
+        return self.inner.owns(ptr);
+    }
+}
+
+impl<A> AllocStat for CheckedAllocator<A>
+where
+    A: AllocStat,
+{
+    fn total_used(&self) -> usize {
+        return self.inner.total_used();
+    }
+
+    fn total_capacity(&self) -> usize {
+        return self.inner.total_capacity();
+    }
+}
+
+impl<A> Drop for CheckedAllocator<A>
+where
+    A: Allocator,
+{
+    fn drop(&mut self) {
+        // Without std there's no way to tell, so only failed checks are skipped
+        #[cfg(feature = "std")]
+        let panicking = std::thread::panicking();
+        #[cfg(not(feature = "std"))]
+        let panicking = false;
+
+        let records = self.records.get_mut();
+        if records.live.is_empty() || panicking || self.failed.get() {
+            return;
+        }
+
+        let mut report = String::new();
+        let _ = write!(
+            report,
+            "CheckedAllocator: {} block(s) leaked:",
+            records.live.len()
+        );
+
+        for (ptr, layout) in records.live.iter() {
+            let _ = write!(
+                report,
+                "\n  {:#x} (size={}, align={})",
+                ptr,
+                layout.size(),
+                layout.align()
+            );
+        }
+
+        core::panic!("{}", report);
+    }
+}
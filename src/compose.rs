@@ -0,0 +1,297 @@
+use crate::alloc_api::*;
+use alloc::alloc::Layout;
+use core::ptr::NonNull;
+
+// Moves a block from one allocator to another, for when a resize can't stay
+// inside the allocator that owns the block.
+unsafe fn move_block<From, To>(
+    from: &From,
+    to: &To,
+    ptr: NonNull<u8>,
+    old_layout: Layout,
+    new_layout: Layout,
+    zeroed: bool,
+) -> Result<NonNull<[u8]>, AllocError>
+where
+    From: Allocator + ?Sized,
+    To: Allocator + ?Sized,
+{
+    let new_ptr = match zeroed {
+        true => to.allocate_zeroed(new_layout)?,
+        false => to.allocate(new_layout)?,
+    };
+
+    let size = core::cmp::min(old_layout.size(), new_layout.size());
+    core::ptr::copy_nonoverlapping(ptr.as_ptr(), new_ptr.as_ptr() as *mut u8, size);
+    from.deallocate(ptr, old_layout);
+
+    return Ok(new_ptr);
+}
+
+/// Tries `primary` first, and uses `secondary` when that fails. Blocks are
+/// routed back to the allocator they came from with `primary.owns`, so the
+/// primary allocator has to be able to answer that.
+pub struct Fallback<P, S>
+where
+    P: Allocator,
+    S: Allocator,
+{
+    pub primary: P,
+    pub secondary: S,
+}
+
+impl<P, S> Fallback<P, S>
+where
+    P: Allocator,
+    S: Allocator,
+{
+    pub const fn new(primary: P, secondary: S) -> Self {
+        return Self { primary, secondary };
+    }
+
+    unsafe fn grow_impl(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+        zeroed: bool,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        if !self.primary.owns(ptr) {
+            return match zeroed {
+                true => self.secondary.grow_zeroed(ptr, old_layout, new_layout),
+                false => self.secondary.grow(ptr, old_layout, new_layout),
+            };
+        }
+
+        let result = match zeroed {
+            true => self.primary.grow_zeroed(ptr, old_layout, new_layout),
+            false => self.primary.grow(ptr, old_layout, new_layout),
+        };
+
+        if let Ok(new_ptr) = result {
+            return Ok(new_ptr);
+        }
+
+        let (from, to) = (&self.primary, &self.secondary);
+        return move_block(from, to, ptr, old_layout, new_layout, zeroed);
+    }
+}
+
+unsafe impl<P, S> Allocator for Fallback<P, S>
+where
+    P: Allocator,
+    S: Allocator,
+{
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        if let Ok(ptr) = self.primary.allocate(layout) {
+            return Ok(ptr);
+        }
+
+        return self.secondary.allocate(layout);
+    }
+
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        if let Ok(ptr) = self.primary.allocate_zeroed(layout) {
+            return Ok(ptr);
+        }
+
+        return self.secondary.allocate_zeroed(layout);
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if self.primary.owns(ptr) {
+            self.primary.deallocate(ptr, layout);
+        } else {
+            self.secondary.deallocate(ptr, layout);
+        }
+    }
+
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return self.grow_impl(ptr, old_layout, new_layout, false);
+    }
+
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return self.grow_impl(ptr, old_layout, new_layout, true);
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        if !self.primary.owns(ptr) {
+            return self.secondary.shrink(ptr, old_layout, new_layout);
+        }
+
+        if let Ok(new_ptr) = self.primary.shrink(ptr, old_layout, new_layout) {
+            return Ok(new_ptr);
+        }
+
+        let (from, to) = (&self.primary, &self.secondary);
+        return move_block(from, to, ptr, old_layout, new_layout, false);
+    }
+
+    fn owns(&self, ptr: NonNull<u8>) -> bool {
+        return self.primary.owns(ptr) || self.secondary.owns(ptr);
+    }
+}
+
+impl<P, S> AllocStat for Fallback<P, S>
+where
+    P: AllocStat,
+    S: AllocStat,
+{
+    fn total_used(&self) -> usize {
+        return self.primary.total_used() + self.secondary.total_used();
+    }
+
+    fn total_capacity(&self) -> usize {
+        return self.primary.total_capacity() + self.secondary.total_capacity();
+    }
+}
+
+/// Sends layouts of up to `N` bytes to `small`, and everything else to
+/// `large`. Blocks that cross the threshold when resized are moved between
+/// the two.
+pub struct Segregator<const N: usize, Small, Large>
+where
+    Small: Allocator,
+    Large: Allocator,
+{
+    pub small: Small,
+    pub large: Large,
+}
+
+impl<const N: usize, Small, Large> Segregator<N, Small, Large>
+where
+    Small: Allocator,
+    Large: Allocator,
+{
+    pub const fn new(small: Small, large: Large) -> Self {
+        return Self { small, large };
+    }
+
+    unsafe fn grow_impl(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+        zeroed: bool,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let (old_size, new_size) = (old_layout.size(), new_layout.size());
+
+        if old_size > N {
+            return match zeroed {
+                true => self.large.grow_zeroed(ptr, old_layout, new_layout),
+                false => self.large.grow(ptr, old_layout, new_layout),
+            };
+        }
+
+        if new_size <= N {
+            return match zeroed {
+                true => self.small.grow_zeroed(ptr, old_layout, new_layout),
+                false => self.small.grow(ptr, old_layout, new_layout),
+            };
+        }
+
+        let (from, to) = (&self.small, &self.large);
+        return move_block(from, to, ptr, old_layout, new_layout, zeroed);
+    }
+}
+
+unsafe impl<const N: usize, Small, Large> Allocator for Segregator<N, Small, Large>
+where
+    Small: Allocator,
+    Large: Allocator,
+{
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        if layout.size() <= N {
+            return self.small.allocate(layout);
+        }
+
+        return self.large.allocate(layout);
+    }
+
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        if layout.size() <= N {
+            return self.small.allocate_zeroed(layout);
+        }
+
+        return self.large.allocate_zeroed(layout);
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if layout.size() <= N {
+            self.small.deallocate(ptr, layout);
+        } else {
+            self.large.deallocate(ptr, layout);
+        }
+    }
+
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return self.grow_impl(ptr, old_layout, new_layout, false);
+    }
+
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return self.grow_impl(ptr, old_layout, new_layout, true);
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let (old_size, new_size) = (old_layout.size(), new_layout.size());
+
+        if old_size <= N {
+            return self.small.shrink(ptr, old_layout, new_layout);
+        }
+
+        if new_size > N {
+            return self.large.shrink(ptr, old_layout, new_layout);
+        }
+
+        let (from, to) = (&self.large, &self.small);
+        return move_block(from, to, ptr, old_layout, new_layout, false);
+    }
+
+    fn owns(&self, ptr: NonNull<u8>) -> bool {
+        return self.small.owns(ptr) || self.large.owns(ptr);
+    }
+}
+
+impl<const N: usize, Small, Large> AllocStat for Segregator<N, Small, Large>
+where
+    Small: AllocStat,
+    Large: AllocStat,
+{
+    fn total_used(&self) -> usize {
+        return self.small.total_used() + self.large.total_used();
+    }
+
+    fn total_capacity(&self) -> usize {
+        return self.small.total_capacity() + self.large.total_capacity();
+    }
+}
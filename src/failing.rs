@@ -0,0 +1,212 @@
+use crate::alloc_api::*;
+use alloc::alloc::Layout;
+use core::cell::Cell;
+use core::ptr::NonNull;
+
+#[derive(Debug, Clone, Copy)]
+pub enum FailurePolicy {
+    /// Fail only the request with this index, counting from zero
+    Nth(usize),
+
+    /// Fail every request that would bring the total number of bytes
+    /// requested so far above this budget
+    AfterBytes(usize),
+
+    /// Fail each request with a probability of `1 / one_in`, using a PRNG
+    /// seeded with `seed`
+    Random { seed: u64, one_in: u64 },
+}
+
+/// Forwards to `A`, but fails requests according to a `FailurePolicy`. The
+/// failures only depend on the sequence of requests, so a failing run can be
+/// replayed by building the allocator with the same policy again.
+///
+/// `allocate`, `allocate_zeroed`, `grow` and `grow_zeroed` count as requests;
+/// `shrink` and `deallocate` always go through.
+///
+/// The counters aren't atomic, so this can't be a `global_allocator!`; use
+/// `Budgeted` to cap a process-wide allocator.
+pub struct FailingAllocator<A>
+where
+    A: Allocator,
+{
+    inner: A,
+    policy: FailurePolicy,
+    requests: Cell<usize>,
+    failures: Cell<usize>,
+    bytes: Cell<usize>,
+    rng: Cell<u64>,
+}
+
+impl<A> FailingAllocator<A>
+where
+    A: Allocator,
+{
+    pub fn new(inner: A, policy: FailurePolicy) -> Self {
+        let s = Self {
+            inner,
+            policy,
+            requests: Cell::new(0),
+            failures: Cell::new(0),
+            bytes: Cell::new(0),
+            rng: Cell::new(0),
+        };
+
+        s.reset();
+
+        return s;
+    }
+
+    pub fn fail_nth(inner: A, n: usize) -> Self {
+        return Self::new(inner, FailurePolicy::Nth(n));
+    }
+
+    pub fn with_budget(inner: A, bytes: usize) -> Self {
+        return Self::new(inner, FailurePolicy::AfterBytes(bytes));
+    }
+
+    pub fn random(inner: A, seed: u64, one_in: u64) -> Self {
+        return Self::new(inner, FailurePolicy::Random { seed, one_in });
+    }
+
+    #[inline(always)]
+    pub fn inner(&self) -> &A {
+        return &self.inner;
+    }
+
+    #[inline(always)]
+    pub fn policy(&self) -> FailurePolicy {
+        return self.policy;
+    }
+
+    /// Number of requests seen so far, including the ones that failed
+    #[inline(always)]
+    pub fn requests(&self) -> usize {
+        return self.requests.get();
+    }
+
+    #[inline(always)]
+    pub fn failures(&self) -> usize {
+        return self.failures.get();
+    }
+
+    /// Starts the policy over from the beginning
+    pub fn reset(&self) {
+        self.requests.set(0);
+        self.failures.set(0);
+        self.bytes.set(0);
+
+        if let FailurePolicy::Random { seed, .. } = self.policy {
+            // xorshift gets stuck on zero
+            self.rng
+                .set(if seed == 0 { 0x9E3779B97F4A7C15 } else { seed });
+        }
+    }
+
+    fn next_random(&self) -> u64 {
+        // xorshift64*
+        let mut x = self.rng.get();
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.rng.set(x);
+
+        return x.wrapping_mul(0x2545F4914F6CDD1D);
+    }
+
+    fn request(&self, bytes: usize) -> Result<(), AllocError> {
+        let index = self.requests.get();
+        self.requests.set(index + 1);
+
+        let fail = match self.policy {
+            FailurePolicy::Nth(n) => index == n,
+            FailurePolicy::AfterBytes(budget) => match self.bytes.get().checked_add(bytes) {
+                Some(total) if total <= budget => {
+                    self.bytes.set(total);
+                    false
+                }
+                _ => true,
+            },
+            FailurePolicy::Random { one_in, .. } => {
+                one_in != 0 && self.next_random().is_multiple_of(one_in)
+            }
+        };
+
+        if fail {
+            self.failures.set(self.failures.get() + 1);
+            return Err(AllocError);
+        }
+
+        return Ok(());
+    }
+}
+
+unsafe impl<A> Allocator for FailingAllocator<A>
+where
+    A: Allocator,
+{
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.request(layout.size())?;
+
+        return self.inner.allocate(layout);
+    }
+
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.request(layout.size())?;
+
+        return self.inner.allocate_zeroed(layout);
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.inner.deallocate(ptr, layout);
+    }
+
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.request(new_layout.size() - old_layout.size())?;
+
+        return self.inner.grow(ptr, old_layout, new_layout);
+    }
+
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.request(new_layout.size() - old_layout.size())?;
+
+        return self.inner.grow_zeroed(ptr, old_layout, new_layout);
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return self.inner.shrink(ptr, old_layout, new_layout);
+    }
+
+    #[inline]
+    fn owns(&self, ptr: NonNull<u8>) -> bool {
+        return self.inner.owns(ptr);
+    }
+}
+
+impl<A> AllocStat for FailingAllocator<A>
+where
+    A: AllocStat,
+{
+    fn total_used(&self) -> usize {
+        return self.inner.total_used();
+    }
+
+    fn total_capacity(&self) -> usize {
+        return self.inner.total_capacity();
+    }
+}
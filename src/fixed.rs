@@ -0,0 +1,219 @@
+use crate::alloc_api::*;
+use crate::stack::*;
+use alloc::alloc::Layout;
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+use core::ptr::NonNull;
+
+/// Bump allocates out of a buffer owned by the caller, e.g. a stack array or
+/// a `static`. Freeing the last allocation gives its memory back, and growing
+/// or shrinking it happens in place. Once the buffer is full, allocating
+/// fails with `AllocError`.
+pub struct FixedBufferAllocator<'a> {
+    base: usize,
+    core: StackCore,
+    buffer: PhantomData<&'a mut [MaybeUninit<u8>]>,
+}
+
+impl<'a> FixedBufferAllocator<'a> {
+    pub fn new(buffer: &'a mut [MaybeUninit<u8>]) -> Self {
+        return Self {
+            base: buffer.as_mut_ptr() as usize,
+            core: StackCore::new(buffer.len()),
+            buffer: PhantomData,
+        };
+    }
+
+    pub fn reset(&mut self) {
+        self.core.set_top(0);
+    }
+}
+
+unsafe impl<'a> Allocator for FixedBufferAllocator<'a> {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let ptr = self.core.alloc(self.base, layout).ok_or(AllocError)?;
+
+        return Ok(sized_slice(ptr, layout.size()));
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.core.pop(self.base, ptr, layout.size());
+    }
+
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let ptr = self
+            .core
+            .grow(self.base, ptr, old_layout, new_layout, false)?;
+
+        return Ok(sized_slice(ptr, new_layout.size()));
+    }
+
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let ptr = self
+            .core
+            .grow(self.base, ptr, old_layout, new_layout, true)?;
+
+        return Ok(sized_slice(ptr, new_layout.size()));
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let ptr = self.core.shrink(self.base, ptr, old_layout, new_layout)?;
+
+        return Ok(sized_slice(ptr, new_layout.size()));
+    }
+
+    fn owns(&self, ptr: NonNull<u8>) -> bool {
+        return self.core.owns(self.base, ptr);
+    }
+}
+
+impl<'a> AllocStat for FixedBufferAllocator<'a> {
+    fn total_used(&self) -> usize {
+        return self.core.used();
+    }
+
+    fn total_capacity(&self) -> usize {
+        return self.core.capacity();
+    }
+}
+
+/// Like `FixedBufferAllocator`, but the buffer is stored inline, so the whole
+/// arena can live on the stack. Allocating goes through the handle from
+/// `allocator()`, which borrows the arena, so it can't move while anything
+/// allocated from it is alive.
+///
+/// ```
+/// use aliu::{InlineArena, Pod};
+///
+/// let arena = InlineArena::<4096>::new();
+/// let mut pod = Pod::<u64, _>::with_allocator(arena.allocator());
+/// pod.push(1);
+/// ```
+///
+/// The arena itself isn't an allocator, since anything owning it could move
+/// it out from under its own blocks:
+///
+/// ```compile_fail
+/// use aliu::{InlineArena, Pod};
+///
+/// let pod = Pod::<u64, _>::with_allocator(InlineArena::<4096>::new());
+/// ```
+pub struct InlineArena<const N: usize> {
+    buffer: UnsafeCell<[MaybeUninit<u8>; N]>,
+    core: StackCore,
+}
+
+impl<const N: usize> InlineArena<N> {
+    pub const fn new() -> Self {
+        return Self {
+            buffer: UnsafeCell::new([MaybeUninit::uninit(); N]),
+            core: StackCore::new(N),
+        };
+    }
+
+    #[inline(always)]
+    pub fn allocator(&self) -> InlineArenaRef<'_, N> {
+        return InlineArenaRef { arena: self };
+    }
+
+    pub fn reset(&mut self) {
+        self.core.set_top(0);
+    }
+
+    #[inline(always)]
+    fn base(&self) -> usize {
+        return self.buffer.get() as usize;
+    }
+}
+
+/// Allocator handle for an `InlineArena`.
+#[derive(Clone, Copy)]
+pub struct InlineArenaRef<'a, const N: usize> {
+    arena: &'a InlineArena<N>,
+}
+
+unsafe impl<'a, const N: usize> Allocator for InlineArenaRef<'a, N> {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let arena = self.arena;
+        let ptr = arena.core.alloc(arena.base(), layout).ok_or(AllocError)?;
+
+        return Ok(sized_slice(ptr, layout.size()));
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        let arena = self.arena;
+        arena.core.pop(arena.base(), ptr, layout.size());
+    }
+
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let arena = self.arena;
+        let ptr = arena
+            .core
+            .grow(arena.base(), ptr, old_layout, new_layout, false)?;
+
+        return Ok(sized_slice(ptr, new_layout.size()));
+    }
+
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let arena = self.arena;
+        let ptr = arena
+            .core
+            .grow(arena.base(), ptr, old_layout, new_layout, true)?;
+
+        return Ok(sized_slice(ptr, new_layout.size()));
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let arena = self.arena;
+        let ptr = arena
+            .core
+            .shrink(arena.base(), ptr, old_layout, new_layout)?;
+
+        return Ok(sized_slice(ptr, new_layout.size()));
+    }
+
+    fn owns(&self, ptr: NonNull<u8>) -> bool {
+        return self.arena.core.owns(self.arena.base(), ptr);
+    }
+}
+
+impl<'a, const N: usize> AllocStat for InlineArenaRef<'a, N> {
+    fn total_used(&self) -> usize {
+        return self.arena.core.used();
+    }
+
+    fn total_capacity(&self) -> usize {
+        return self.arena.core.capacity();
+    }
+}
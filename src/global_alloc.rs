@@ -0,0 +1,174 @@
+use crate::alloc_api::*;
+use alloc::alloc::{GlobalAlloc, Layout};
+use core::ops::Deref;
+use core::ptr::{null_mut, NonNull};
+
+/// Installs an aliu allocator as the process-wide `#[global_allocator]`.
+///
+#[cfg_attr(feature = "std", doc = "```")]
+#[cfg_attr(not(feature = "std"), doc = "```ignore")]
+/// use aliu::{AllocStat, System, Tracked};
+///
+/// aliu::global_allocator!(HEAP: Tracked<System> = Tracked::new(System));
+///
+/// fn main() {
+///     let v = vec![1u8; 100];
+///     assert!(HEAP.total_used() >= 100);
+/// }
+/// ```
+///
+/// With no arguments, installs `GlobalAdapter<System>`, which needs the `std`
+/// feature.
+#[macro_export]
+macro_rules! global_allocator {
+    () => {
+        $crate::global_allocator!(ALIU_GLOBAL_ALLOCATOR: $crate::System = $crate::System);
+    };
+
+    ($name:ident : $ty:ty = $init:expr $(;)?) => {
+        #[global_allocator]
+        static $name: $crate::GlobalAdapter<$ty> = $crate::GlobalAdapter::new($init);
+    };
+}
+
+/// Same as `Global`, but always goes to the platform allocator instead of the
+/// registered `#[global_allocator]`. This is what should sit underneath a
+/// `GlobalAdapter`, since going through `Global` there would recurse forever.
+#[cfg(feature = "std")]
+#[derive(Clone, Copy)]
+pub struct System;
+
+#[cfg(feature = "std")]
+unsafe impl Allocator for System {
+    #[inline]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        return heap_allocate(&std::alloc::System, layout, false);
+    }
+
+    #[inline]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        return heap_allocate(&std::alloc::System, layout, true);
+    }
+
+    #[inline]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        heap_deallocate(&std::alloc::System, ptr, layout);
+    }
+
+    #[inline]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return heap_grow(&std::alloc::System, ptr, old_layout, new_layout, false);
+    }
+
+    #[inline]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return heap_grow(&std::alloc::System, ptr, old_layout, new_layout, true);
+    }
+
+    #[inline]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return heap_shrink(&std::alloc::System, ptr, old_layout, new_layout);
+    }
+}
+
+/// Implements `GlobalAlloc` on top of an aliu `Allocator`. The allocator has
+/// to be `Sync` to be used in a `static`, and it must not allocate through
+/// `Global` itself. `Tracked` and `Budgeted` qualify; wrappers built on `Cell`,
+/// like `FailingAllocator` and `CheckedAllocator`, don't.
+pub struct GlobalAdapter<A>
+where
+    A: Allocator,
+{
+    inner: A,
+}
+
+impl<A> GlobalAdapter<A>
+where
+    A: Allocator,
+{
+    pub const fn new(inner: A) -> Self {
+        return Self { inner };
+    }
+
+    #[inline(always)]
+    pub const fn inner(&self) -> &A {
+        return &self.inner;
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for GlobalAdapter<System> {
+    fn default() -> Self {
+        return Self::new(System);
+    }
+}
+
+impl<A> Deref for GlobalAdapter<A>
+where
+    A: Allocator,
+{
+    type Target = A;
+
+    #[inline(always)]
+    fn deref(&self) -> &A {
+        return &self.inner;
+    }
+}
+
+unsafe impl<A> GlobalAlloc for GlobalAdapter<A>
+where
+    A: Allocator,
+{
+    #[inline]
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        return match self.inner.allocate(layout) {
+            Ok(ptr) => ptr.as_ptr() as *mut u8,
+            Err(_) => null_mut(),
+        };
+    }
+
+    #[inline]
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        return match self.inner.allocate_zeroed(layout) {
+            Ok(ptr) => ptr.as_ptr() as *mut u8,
+            Err(_) => null_mut(),
+        };
+    }
+
+    #[inline]
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.inner.deallocate(NonNull::new_unchecked(ptr), layout);
+    }
+
+    #[inline]
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+        let ptr = NonNull::new_unchecked(ptr);
+
+        let result = if new_size >= layout.size() {
+            self.inner.grow(ptr, layout, new_layout)
+        } else {
+            self.inner.shrink(ptr, layout, new_layout)
+        };
+
+        return match result {
+            Ok(ptr) => ptr.as_ptr() as *mut u8,
+            Err(_) => null_mut(),
+        };
+    }
+}
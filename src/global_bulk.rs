@@ -1,12 +1,13 @@
 use crate::alloc_api::*;
 use crate::basic::*;
-use std::ffi::*;
 
 type Ptr = *const ();
 
+// Every backend's `map_region` returns zeroed memory, like a fresh anonymous
+// mapping, and callers rely on it
 pub use os::*;
 
-#[cfg(target_family = "unix")]
+#[cfg(all(feature = "os", target_family = "unix"))]
 mod os {
     use super::*;
 
@@ -54,7 +55,7 @@ mod os {
     }
 }
 
-#[cfg(target_family = "windows")]
+#[cfg(all(feature = "os", target_family = "windows"))]
 mod os {
     use super::*;
 
@@ -89,26 +90,32 @@ mod os {
     }
 }
 
-#[cfg(all(target_family = "wasm", not(target_os = "emscripten")))]
+// Without virtual memory, regions are just page-aligned blocks from `Global`
+#[cfg(any(
+    not(feature = "os"),
+    all(target_family = "wasm", not(target_os = "emscripten"))
+))]
 mod os {
     use super::*;
     use core::alloc::Layout;
     use core::ptr::NonNull;
 
+    const PAGE_SIZE: usize = 4096;
+
     pub unsafe fn map_region(base: Ptr, size: usize) -> Result<Ptr, AllocError> {
         if !base.is_null() {
-            panic!("aliu::map_region caused an error: can't give value for base pointer on webassembly");
+            panic!("aliu::map_region caused an error: can't give value for base pointer without an OS");
         }
 
-        let layout = Layout::from_size_align(size, 8).map_err(|e| AllocError)?;
-        let alloc = Global.allocate(layout)?;
+        let layout = Layout::from_size_align(size, PAGE_SIZE).map_err(|e| AllocError)?;
+        let alloc = Global.allocate_zeroed(layout)?;
 
         let ptr = &*alloc.as_ptr();
         return Ok(ptr.as_ptr() as *const ());
     }
 
     pub unsafe fn unmap_region(base: Ptr, size: usize) -> Result<(), AllocError> {
-        let layout = Layout::from_size_align(size, 8).map_err(|e| AllocError)?;
+        let layout = Layout::from_size_align(size, PAGE_SIZE).map_err(|e| AllocError)?;
         let ptr = NonNull::new(base as *mut _).ok_or(AllocError)?;
         Global.deallocate(ptr, layout);
 
@@ -2,17 +2,93 @@ use crate::*;
 use core::borrow::Borrow;
 use core::fmt;
 use core::hash::{BuildHasher, Hash, Hasher};
-use std::collections::hash_map::{DefaultHasher, HashMap};
+
+#[cfg(feature = "std")]
+use std::collections::HashMap;
 
 #[derive(Clone, Copy)]
 pub struct DetState;
 
 impl BuildHasher for DetState {
-    type Hasher = DefaultHasher;
+    type Hasher = DetHasher;
+
+    #[inline]
+    fn build_hasher(&self) -> DetHasher {
+        return DetHasher::default();
+    }
+}
+
+/// Hasher with no random state, so that hashes are the same across runs and
+/// builds, with or without std. It's fast but not DoS resistant.
+#[derive(Clone, Copy, Default)]
+pub struct DetHasher {
+    hash: u64,
+}
+
+impl DetHasher {
+    const SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
+
+    #[inline(always)]
+    fn add(&mut self, word: u64) {
+        self.hash = (self.hash.rotate_left(5) ^ word).wrapping_mul(Self::SEED);
+    }
+}
+
+impl Hasher for DetHasher {
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        let mut chunks = bytes.chunks_exact(8);
+        for chunk in &mut chunks {
+            let mut word = [0; 8];
+            word.copy_from_slice(chunk);
+            self.add(u64::from_le_bytes(word));
+        }
+
+        let rest = chunks.remainder();
+        if !rest.is_empty() {
+            let mut word = [0; 8];
+            word[..rest.len()].copy_from_slice(rest);
+            self.add(u64::from_le_bytes(word) ^ ((rest.len() as u64) << 56));
+        }
+    }
+
+    #[inline]
+    fn write_u8(&mut self, i: u8) {
+        self.add(i as u64);
+    }
+
+    #[inline]
+    fn write_u16(&mut self, i: u16) {
+        self.add(i as u64);
+    }
+
+    #[inline]
+    fn write_u32(&mut self, i: u32) {
+        self.add(i as u64);
+    }
+
+    #[inline]
+    fn write_u64(&mut self, i: u64) {
+        self.add(i);
+    }
+
+    #[inline]
+    fn write_usize(&mut self, i: usize) {
+        self.add(i as u64);
+    }
 
+    // The multiply only mixes upwards, and slots are picked with the low
+    // bits, so finish with the murmur3 finalizer.
     #[inline]
-    fn build_hasher(&self) -> DefaultHasher {
-        return DefaultHasher::new();
+    fn finish(&self) -> u64 {
+        let mut hash = self.hash;
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+        hash ^= hash >> 33;
+
+        return hash;
     }
 }
 
@@ -43,6 +119,7 @@ where
     K: Eq + Hash + Copy + 'a,
     V: Copy + 'a,
 {
+    #[cfg(feature = "std")]
     pub fn new(frame: impl Allocator, data: &HashMap<K, V>) -> Self {
         return Self::with_state(frame, data, DetState);
     }
@@ -69,6 +146,7 @@ where
     V: Copy + 'a,
     State: BuildHasher,
 {
+    #[cfg(feature = "std")]
     pub fn with_state(frame: impl Allocator, data: &HashMap<K, V>, state: State) -> Self {
         let capa = data.len() * 3 / 2;
         return Self::with_state_iter(frame, capa, data.iter().map(|(&k, &v)| (k, v)), state);
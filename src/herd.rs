@@ -0,0 +1,225 @@
+use crate::alloc_api::*;
+use crate::bump::*;
+use crate::pod::*;
+use alloc::alloc::Layout;
+use alloc::boxed::Box;
+use core::cell::Cell;
+use core::ptr::{self, NonNull};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::thread::{self, ThreadId};
+
+type Arena = Mutex<BucketList>;
+
+// Herds are told apart by an ID rather than their address, since a new herd
+// can end up where a dropped one used to be. Zero is never handed out.
+static NEXT_HERD_ID: AtomicUsize = AtomicUsize::new(1);
+
+std::thread_local! {
+    // The arena this thread used last, and the ID of the herd it belongs to
+    static LAST_ARENA: Cell<(usize, *const Arena)> = const { Cell::new((0, ptr::null())) };
+}
+
+#[inline(always)]
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    return mutex.lock().unwrap_or_else(|e| e.into_inner());
+}
+
+/// Gives every thread its own `BucketList`, created the first time the thread
+/// asks for its `Member`. The herd owns all of the buckets until it's dropped,
+/// so memory from one member can be handed to other threads freely.
+///
+/// Each thread remembers the arena it used last, so looking up a member only
+/// takes the herd's lock the first time a thread asks for it (or after it
+/// switches between herds). Each arena sits behind its own lock, which is only
+/// ever contended while the herd adds up stats across members.
+pub struct ArenaHerd {
+    id: usize,
+    arenas: Mutex<Pod<(ThreadId, NonNull<Arena>)>>,
+    builder: BucketListBuilder,
+}
+
+unsafe impl Send for ArenaHerd {}
+unsafe impl Sync for ArenaHerd {}
+
+impl ArenaHerd {
+    pub fn new() -> Self {
+        return Self::with_builder(BucketListBuilder::new());
+    }
+
+    /// Every member's arena is made with `builder`.
+    pub fn with_builder(builder: BucketListBuilder) -> Self {
+        return Self {
+            id: NEXT_HERD_ID.fetch_add(1, Ordering::Relaxed),
+            arenas: Mutex::new(Pod::new()),
+            builder,
+        };
+    }
+
+    pub fn member(&self) -> Member<'_> {
+        let (herd, arena) = LAST_ARENA.get();
+        if herd == self.id {
+            // Arenas aren't freed until the herd is dropped
+            return Member {
+                arena: unsafe { &*arena },
+            };
+        }
+
+        return self.member_slow();
+    }
+
+    #[cold]
+    fn member_slow(&self) -> Member<'_> {
+        let id = thread::current().id();
+        let mut arenas = lock(&self.arenas);
+
+        let arena = match arenas.iter().find(|&&(owner, _)| owner == id) {
+            Some(&(_, arena)) => arena,
+            None => {
+                let arena = NonNull::from(Box::leak(Box::new(Mutex::new(self.builder.build()))));
+                arenas.push((id, arena));
+
+                arena
+            }
+        };
+
+        LAST_ARENA.set((self.id, arena.as_ptr()));
+
+        return Member {
+            arena: unsafe { arena.as_ref() },
+        };
+    }
+
+    pub fn member_count(&self) -> usize {
+        return lock(&self.arenas).len();
+    }
+
+    fn sum(&self, f: impl Fn(&BucketList) -> usize) -> usize {
+        let arenas = lock(&self.arenas);
+
+        let mut total = 0;
+        for &(_, arena) in arenas.iter() {
+            total += f(&lock(unsafe { arena.as_ref() }));
+        }
+
+        return total;
+    }
+}
+
+impl Drop for ArenaHerd {
+    fn drop(&mut self) {
+        let arenas = self.arenas.get_mut().unwrap_or_else(|e| e.into_inner());
+
+        for &(_, arena) in arenas.iter() {
+            unsafe { drop(Box::from_raw(arena.as_ptr())) };
+        }
+    }
+}
+
+/// Allocates from the calling thread's member.
+unsafe impl Allocator for ArenaHerd {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        return self.member().allocate(layout);
+    }
+
+    // deallocation doesn't do anything
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {}
+
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return self.member().grow(ptr, old_layout, new_layout);
+    }
+
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return self.member().grow_zeroed(ptr, old_layout, new_layout);
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return self.member().shrink(ptr, old_layout, new_layout);
+    }
+
+    fn owns(&self, ptr: NonNull<u8>) -> bool {
+        return self.sum(|arena| arena.owns(ptr) as usize) != 0;
+    }
+}
+
+/// Totals across every member.
+impl AllocStat for ArenaHerd {
+    fn total_used(&self) -> usize {
+        return self.sum(|arena| arena.total_used());
+    }
+
+    fn total_capacity(&self) -> usize {
+        return self.sum(|arena| arena.total_capacity());
+    }
+}
+
+/// One thread's share of an `ArenaHerd`.
+#[derive(Clone, Copy)]
+pub struct Member<'a> {
+    arena: &'a Arena,
+}
+
+unsafe impl<'a> Allocator for Member<'a> {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        return lock(self.arena).allocate(layout);
+    }
+
+    // deallocation doesn't do anything
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {}
+
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return lock(self.arena).grow(ptr, old_layout, new_layout);
+    }
+
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return lock(self.arena).grow_zeroed(ptr, old_layout, new_layout);
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return lock(self.arena).shrink(ptr, old_layout, new_layout);
+    }
+
+    fn owns(&self, ptr: NonNull<u8>) -> bool {
+        return lock(self.arena).owns(ptr);
+    }
+}
+
+impl<'a> AllocStat for Member<'a> {
+    fn total_used(&self) -> usize {
+        return lock(self.arena).total_used();
+    }
+
+    fn total_capacity(&self) -> usize {
+        return lock(self.arena).total_capacity();
+    }
+}
@@ -1,9 +1,26 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+#![cfg_attr(feature = "nightly", feature(allocator_api))]
 // Long-term
 #![allow(dead_code)]
 #![allow(unused_variables)]
 #![allow(unused_macros)]
 #![allow(unused_braces)]
 #![allow(non_upper_case_globals)]
+#![allow(clippy::needless_return)]
+#![allow(clippy::missing_safety_doc)]
+#![allow(clippy::new_without_default)]
+#![allow(clippy::len_without_is_empty)]
+#![allow(clippy::len_zero)]
+#![allow(clippy::new_ret_no_self)]
+#![allow(clippy::wrong_self_convention)]
+#![allow(clippy::assign_op_pattern)]
+#![allow(clippy::manual_hash_one)]
+#![allow(clippy::multiple_bound_locations)]
+#![allow(clippy::manual_slice_size_calculation)]
+#![allow(clippy::borrow_deref_ref)]
+#![allow(clippy::explicit_auto_deref)]
+#![allow(clippy::useless_conversion)]
+#![allow(clippy::needless_borrow)]
 // Short-term allows
 /* */
 #![allow(unused_imports)]
@@ -38,18 +55,58 @@ macro_rules! unreachable {
 mod basic;
 
 mod alloc_api;
+mod global_alloc;
 mod global_bulk;
 
 #[macro_use]
 mod pod;
 
+mod buddy;
+mod budget;
 mod bump;
+mod checked;
+mod compose;
+mod failing;
+mod fixed;
 mod fswatch;
 mod hashref;
+#[cfg(feature = "std")]
+mod herd;
+mod pool;
+mod slab;
+mod stack;
+#[cfg(feature = "std")]
+mod sync_bump;
+mod tlsf;
+mod tracked;
+mod typed_arena;
+
+#[cfg(feature = "nightly")]
+mod nightly;
 
 pub use alloc_api::*;
 pub use basic::*;
+pub use buddy::*;
+pub use budget::*;
 pub use bump::*;
+pub use checked::*;
+pub use compose::*;
+pub use failing::*;
+pub use fixed::*;
+pub use global_alloc::*;
 pub use global_bulk::*;
 pub use hashref::*;
+#[cfg(feature = "std")]
+pub use herd::*;
 pub use pod::*;
+pub use pool::*;
+pub use slab::*;
+pub use stack::*;
+#[cfg(feature = "std")]
+pub use sync_bump::*;
+pub use tlsf::*;
+pub use tracked::*;
+pub use typed_arena::*;
+
+#[cfg(feature = "nightly")]
+pub use nightly::*;
@@ -0,0 +1,156 @@
+use crate::alloc_api::*;
+use crate::bump::*;
+use alloc::alloc::Layout;
+use core::ptr::NonNull;
+
+// Coherence rules out real blanket impls in either direction, since the std
+// trait is foreign and both traits already have impls for `&A`. Instead, any
+// allocator can go through one of the wrappers below, and the allocators in
+// this crate that are commonly passed to std collections implement the std
+// trait directly.
+
+/// Lets an aliu allocator be used with `Vec::new_in`, `Box::new_in`, etc.
+#[derive(Clone, Copy)]
+pub struct StdAllocator<A>(pub A)
+where
+    A: Allocator;
+
+/// Lets a std allocator be used with `Pod` and the rest of this crate.
+#[derive(Clone, Copy)]
+pub struct FromStdAllocator<A>(pub A)
+where
+    A: core::alloc::Allocator;
+
+#[inline(always)]
+fn to_std(
+    result: Result<NonNull<[u8]>, AllocError>,
+) -> Result<NonNull<[u8]>, core::alloc::AllocError> {
+    return result.map_err(|_| core::alloc::AllocError);
+}
+
+#[inline(always)]
+fn from_std(
+    result: Result<NonNull<[u8]>, core::alloc::AllocError>,
+) -> Result<NonNull<[u8]>, AllocError> {
+    return result.map_err(|_| AllocError);
+}
+
+macro_rules! impl_std_allocator {
+    ( $( [ $($generics:tt)* ] $ty:ty => |$s:ident| $inner:expr ; )+ ) => {
+        $(
+            unsafe impl<$($generics)*> core::alloc::Allocator for $ty {
+                #[inline]
+                fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, core::alloc::AllocError> {
+                    let $s = self;
+                    return to_std(Allocator::allocate($inner, layout));
+                }
+
+                #[inline]
+                fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, core::alloc::AllocError> {
+                    let $s = self;
+                    return to_std(Allocator::allocate_zeroed($inner, layout));
+                }
+
+                #[inline]
+                unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+                    let $s = self;
+                    Allocator::deallocate($inner, ptr, layout);
+                }
+
+                #[inline]
+                unsafe fn grow(
+                    &self,
+                    ptr: NonNull<u8>,
+                    old_layout: Layout,
+                    new_layout: Layout,
+                ) -> Result<NonNull<[u8]>, core::alloc::AllocError> {
+                    let $s = self;
+                    return to_std(Allocator::grow($inner, ptr, old_layout, new_layout));
+                }
+
+                #[inline]
+                unsafe fn grow_zeroed(
+                    &self,
+                    ptr: NonNull<u8>,
+                    old_layout: Layout,
+                    new_layout: Layout,
+                ) -> Result<NonNull<[u8]>, core::alloc::AllocError> {
+                    let $s = self;
+                    return to_std(Allocator::grow_zeroed($inner, ptr, old_layout, new_layout));
+                }
+
+                #[inline]
+                unsafe fn shrink(
+                    &self,
+                    ptr: NonNull<u8>,
+                    old_layout: Layout,
+                    new_layout: Layout,
+                ) -> Result<NonNull<[u8]>, core::alloc::AllocError> {
+                    let $s = self;
+                    return to_std(Allocator::shrink($inner, ptr, old_layout, new_layout));
+                }
+            }
+        )+
+    };
+}
+
+impl_std_allocator! {
+    [A: Allocator] StdAllocator<A> => |s| &s.0;
+    [A: Allocator] BucketList<A> => |s| s;
+    ['a, A: Allocator] ScopedBump<'a, A> => |s| s;
+}
+
+#[cfg(feature = "std")]
+impl_std_allocator! {
+    [] crate::sync_bump::SyncBucketList => |s| s;
+}
+
+unsafe impl<A> Allocator for FromStdAllocator<A>
+where
+    A: core::alloc::Allocator,
+{
+    #[inline]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        return from_std(self.0.allocate(layout));
+    }
+
+    #[inline]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        return from_std(self.0.allocate_zeroed(layout));
+    }
+
+    #[inline]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.0.deallocate(ptr, layout);
+    }
+
+    #[inline]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return from_std(self.0.grow(ptr, old_layout, new_layout));
+    }
+
+    #[inline]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return from_std(self.0.grow_zeroed(ptr, old_layout, new_layout));
+    }
+
+    #[inline]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return from_std(self.0.shrink(ptr, old_layout, new_layout));
+    }
+}
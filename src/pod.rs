@@ -126,23 +126,35 @@ where
     }
 
     pub fn extend_from_slice(&mut self, data: &[T]) {
+        unwrap_pod(self.try_extend_from_slice(data));
+    }
+
+    pub fn try_extend_from_slice(&mut self, data: &[T]) -> Result<(), PodError> {
         let len = data.len();
-        self.raw.reserve_additional(&self.allocator, len);
+        self.raw.try_reserve_additional(&self.allocator, len)?;
 
         let ptr = self.raw.ptr(self.raw.length) as *mut T;
         let to_space = unsafe { core::slice::from_raw_parts_mut(ptr, len) };
         to_space.copy_from_slice(data);
 
         self.raw.length += len;
+
+        return Ok(());
     }
 
     pub fn push(&mut self, t: T) {
-        self.raw.reserve_additional(&self.allocator, 1);
+        unwrap_pod(self.try_push(t));
+    }
+
+    pub fn try_push(&mut self, t: T) -> Result<(), PodError> {
+        self.raw.try_reserve_additional(&self.allocator, 1)?;
 
         let ptr = self.raw.ptr(self.raw.length) as *mut T;
         self.raw.length += 1;
 
         unsafe { *ptr = t };
+
+        return Ok(());
     }
 
     pub fn leak<'b>(self) -> &'b mut [T] {
@@ -160,7 +172,11 @@ where
     }
 
     pub fn insert(&mut self, i: usize, value: T) {
-        self.raw.reserve_additional(&self.allocator, 1);
+        unwrap_pod(self.try_insert(i, value));
+    }
+
+    pub fn try_insert(&mut self, i: usize, value: T) -> Result<(), PodError> {
+        self.raw.try_reserve_additional(&self.allocator, 1)?;
         self.raw.length += 1;
 
         if self.raw.copy_range(i..self.raw.length, i + 1) {
@@ -169,16 +185,28 @@ where
 
         let ptr = self.raw.ptr(i) as *mut T;
         unsafe { *ptr = value };
+
+        return Ok(());
     }
 
     pub fn splice(&mut self, range: impl RangeBounds<usize>, values: &[T]) {
+        unwrap_pod(self.try_splice(range, values));
+    }
+
+    pub fn try_splice(
+        &mut self,
+        range: impl RangeBounds<usize>,
+        values: &[T],
+    ) -> Result<(), PodError> {
         let range = self.raw.translate_range(range);
         let len = values.len();
 
-        let ptr = self.raw.splice_ptr(&self.allocator, range, len) as *mut T;
+        let ptr = self.raw.try_splice_ptr(&self.allocator, range, len)? as *mut T;
         let slice = unsafe { core::slice::from_raw_parts_mut(ptr, len) };
 
         slice.copy_from_slice(values);
+
+        return Ok(());
     }
 
     pub fn pop(&mut self) -> Option<T> {
@@ -202,13 +230,19 @@ where
     }
 
     pub fn push_repeat(&mut self, t: T, repeat: usize) {
-        self.raw.reserve_additional(&self.allocator, repeat);
+        unwrap_pod(self.try_push_repeat(t, repeat));
+    }
+
+    pub fn try_push_repeat(&mut self, t: T, repeat: usize) -> Result<(), PodError> {
+        self.raw.try_reserve_additional(&self.allocator, repeat)?;
 
         let ptr = self.raw.ptr(self.raw.length) as *mut T;
         let data = unsafe { core::slice::from_raw_parts_mut(ptr, repeat) };
         data.fill(t);
 
         self.raw.length += repeat;
+
+        return Ok(());
     }
 
     #[inline(always)]
@@ -222,8 +256,12 @@ where
     }
 
     pub fn resize(&mut self, size: usize, fill: T) {
+        unwrap_pod(self.try_resize(size, fill));
+    }
+
+    pub fn try_resize(&mut self, size: usize, fill: T) -> Result<(), PodError> {
         if size > self.raw.capacity {
-            self.raw.realloc(&self.allocator, size);
+            self.raw.try_realloc(&self.allocator, size)?;
         }
 
         if size > self.raw.length {
@@ -233,6 +271,8 @@ where
         }
 
         self.raw.length = size;
+
+        return Ok(());
     }
 
     #[cfg_attr(not(debug_assertions), inline(always))]
@@ -256,7 +296,7 @@ where
     }
 
     pub fn extend_uninit(&mut self, extend: usize) -> (&[T], &mut [T]) {
-        self.raw.reserve_additional(&self.allocator, extend);
+        unwrap_pod(self.raw.try_reserve_additional(&self.allocator, extend));
 
         let old_len = self.raw.length;
         self.raw.length += extend;
@@ -269,7 +309,11 @@ where
     }
 
     pub fn reserve(&mut self, additional: usize) {
-        self.raw.reserve_additional(&self.allocator, additional);
+        unwrap_pod(self.try_reserve(additional));
+    }
+
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), PodError> {
+        return self.raw.try_reserve_additional(&self.allocator, additional);
     }
 
     pub fn shrink_to_fit(&mut self) {
@@ -485,6 +529,33 @@ where
 //
 // ----------------------------------------------------------------------------
 
+/// Error returned by the fallible `try_*` methods on `Pod`. When one of these
+/// is returned, the `Pod` is left exactly as it was before the call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PodError {
+    /// The requested capacity doesn't fit in a `Layout`
+    LayoutFailure,
+
+    /// The allocator returned `AllocError`
+    AllocFailure,
+}
+
+impl core::fmt::Display for PodError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        return match self {
+            PodError::LayoutFailure => write!(f, "layout failure"),
+            PodError::AllocFailure => write!(f, "allocation failure"),
+        };
+    }
+}
+
+#[inline(always)]
+fn unwrap_pod(result: Result<(), PodError>) {
+    if let Err(e) = result {
+        panic!("{}", e);
+    }
+}
+
 struct RawPod {
     data: NonNull<u8>,
     info: DataInfo,
@@ -496,13 +567,20 @@ impl RawPod {
     fn new(info: DataInfo) -> Self {
         // We use the same trick that std::vec::Vec uses
         return Self {
-            data: NonNull::dangling(),
+            data: Self::dangling(),
             info,
             length: 0,
             capacity: 0,
         };
     }
 
+    // Has to be aligned for any `T`, even when the capacity is zero, since we
+    // still make (empty) slices out of it
+    #[inline(always)]
+    fn dangling() -> NonNull<u8> {
+        return unsafe { NonNull::new_unchecked(8 as *mut u8) };
+    }
+
     fn range_is_valid(&self, start: usize, end: usize) -> bool {
         return start <= end && end <= self.length;
     }
@@ -545,52 +623,62 @@ impl RawPod {
         return false;
     }
 
-    fn reserve_additional(&mut self, alloc: &dyn Allocator, additional: usize) {
-        return self.reserve_total(alloc, self.length + additional);
+    fn try_reserve_additional(
+        &mut self,
+        alloc: &dyn Allocator,
+        additional: usize,
+    ) -> Result<(), PodError> {
+        let needed = self
+            .length
+            .checked_add(additional)
+            .ok_or(PodError::LayoutFailure)?;
+
+        return self.try_reserve_total(alloc, needed);
     }
 
-    fn reserve_total(&mut self, alloc: &dyn Allocator, needed: usize) {
+    fn try_reserve_total(&mut self, alloc: &dyn Allocator, needed: usize) -> Result<(), PodError> {
         if needed <= self.capacity {
-            return;
+            return Ok(());
         }
 
         let new_capacity = core::cmp::max(needed, self.capacity * 3 / 2);
-        self.realloc(alloc, new_capacity);
+        return self.try_realloc(alloc, new_capacity);
     }
 
-    fn splice_ptr(&mut self, alloc: &dyn Allocator, range: Range<usize>, len: usize) -> *mut u8 {
+    fn try_splice_ptr(
+        &mut self,
+        alloc: &dyn Allocator,
+        range: Range<usize>,
+        len: usize,
+    ) -> Result<*mut u8, PodError> {
         let (start, end) = (range.start, range.end);
 
         if !self.range_is_valid(start, end) {
             panic!("invalid range");
         }
 
-        let copy_target = start + len;
+        let copy_target = start.checked_add(len).ok_or(PodError::LayoutFailure)?;
         let range_to_copy = end..self.length;
-        let final_len = copy_target + range_to_copy.len();
-        self.reserve_total(alloc, final_len);
+        let final_len = copy_target
+            .checked_add(range_to_copy.len())
+            .ok_or(PodError::LayoutFailure)?;
+        self.try_reserve_total(alloc, final_len)?;
 
         self.copy_range(range_to_copy, copy_target);
         self.length = final_len;
 
-        return self.ptr(start);
+        return Ok(self.ptr(start));
     }
 
     fn realloc(&mut self, alloc: &dyn Allocator, elem_capacity: usize) {
-        match self.try_realloc(alloc, elem_capacity) {
-            Ok(()) => {}
-            Err(e) => {
-                panic!("{}", e);
-            }
-        }
+        unwrap_pod(self.try_realloc(alloc, elem_capacity));
     }
 
-    fn try_realloc(
-        &mut self,
-        alloc: &dyn Allocator,
-        elem_capacity: usize,
-    ) -> Result<(), &'static str> {
+    fn try_realloc(&mut self, alloc: &dyn Allocator, elem_capacity: usize) -> Result<(), PodError> {
         let (size, align) = (self.info.size, 8);
+        let new_size = size
+            .checked_mul(elem_capacity)
+            .ok_or(PodError::LayoutFailure)?;
         let get_info = move |mut data: NonNull<[u8]>| -> (NonNull<u8>, usize) {
             let data = unsafe { data.as_mut() };
             let capacity = data.len() / size;
@@ -601,7 +689,7 @@ impl RawPod {
 
         // We use the same trick that std::vec::Vec uses, where a capacity of
         // zero means we don't look at the data pointer at all
-        let (data, capacity) = match (size * self.capacity, size * elem_capacity) {
+        let (data, capacity) = match (size * self.capacity, new_size) {
             (x, y) if x == y => return Ok(()),
             (0, 0) => {
                 self.capacity = elem_capacity;
@@ -609,26 +697,26 @@ impl RawPod {
             }
 
             (prev_size, 0) => {
-                let layout =
-                    Layout::from_size_align(prev_size, align).map_err(|_| "layout failure")?;
+                let layout = Layout::from_size_align(prev_size, align)
+                    .map_err(|_| PodError::LayoutFailure)?;
                 unsafe { alloc.deallocate(self.data, layout) };
 
-                (NonNull::dangling(), elem_capacity)
+                (Self::dangling(), elem_capacity)
             }
 
             (0, new_size) => {
-                let layout =
-                    Layout::from_size_align(new_size, align).map_err(|_| "layout failure")?;
-                let data = alloc.allocate(layout).map_err(|_| "allocation failure")?;
+                let layout = Layout::from_size_align(new_size, align)
+                    .map_err(|_| PodError::LayoutFailure)?;
+                let data = alloc.allocate(layout).map_err(|_| PodError::AllocFailure)?;
 
                 get_info(data)
             }
 
             (prev_size, new_size) => {
-                let prev_layout =
-                    Layout::from_size_align(prev_size, align).map_err(|_| "layout failure")?;
-                let new_layout =
-                    Layout::from_size_align(new_size, align).map_err(|_| "layout failure")?;
+                let prev_layout = Layout::from_size_align(prev_size, align)
+                    .map_err(|_| PodError::LayoutFailure)?;
+                let new_layout = Layout::from_size_align(new_size, align)
+                    .map_err(|_| PodError::LayoutFailure)?;
 
                 let result = unsafe {
                     if new_size > prev_size {
@@ -638,7 +726,7 @@ impl RawPod {
                     }
                 };
 
-                let data = result.map_err(|_| "allocation failure")?;
+                let data = result.map_err(|_| PodError::AllocFailure)?;
 
                 get_info(data)
             }
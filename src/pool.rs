@@ -0,0 +1,222 @@
+use crate::alloc_api::*;
+use crate::global_bulk::*;
+use crate::pod::*;
+use alloc::alloc::Layout;
+use core::cell::Cell;
+use core::ptr::NonNull;
+
+#[derive(Clone, Copy)]
+struct Page {
+    ptr: NonNull<u8>,
+    size: usize,
+}
+
+struct FreeBlock {
+    next: Option<NonNull<FreeBlock>>,
+}
+
+/// Hands out blocks of a single size, carved out of pages from `map_region`.
+/// Freed blocks go on an intrusive free list and get reused before any new
+/// memory is carved. Pages are only given back when the pool is dropped.
+///
+/// Requests that don't fit in a block fail with `AllocError`; resizes within
+/// a block happen in place.
+pub struct Pool {
+    block_size: usize,
+    block_align: usize,
+    page_size: usize,
+    free: Cell<Option<NonNull<FreeBlock>>>,
+    pages: Cell<Pod<Page>>,
+    next: Cell<usize>,
+    end: Cell<usize>,
+    live: Cell<usize>,
+}
+
+impl Pool {
+    pub const DEFAULT_PAGE_SIZE: usize = 64 * 1024;
+
+    pub fn new(layout: Layout) -> Self {
+        return Self::with_page_size(layout, Self::DEFAULT_PAGE_SIZE);
+    }
+
+    pub fn for_type<T>() -> Self {
+        return Self::new(Layout::new::<T>());
+    }
+
+    pub fn with_page_size(layout: Layout, page_size: usize) -> Self {
+        // Every block has to be able to hold a free list entry
+        let block_align = core::cmp::max(layout.align(), core::mem::align_of::<FreeBlock>());
+        let block_size = core::cmp::max(layout.size(), core::mem::size_of::<FreeBlock>());
+        let block_size = (block_size + block_align - 1) & !(block_align - 1);
+
+        // Leave room to align the first block in the page
+        let page_size = core::cmp::max(page_size, block_size + block_align);
+
+        return Self {
+            block_size,
+            block_align,
+            page_size,
+            free: Cell::new(None),
+            pages: Cell::new(Pod::new()),
+            next: Cell::new(0),
+            end: Cell::new(0),
+            live: Cell::new(0),
+        };
+    }
+
+    #[inline(always)]
+    pub fn block_size(&self) -> usize {
+        return self.block_size;
+    }
+
+    #[inline(always)]
+    fn fits(&self, layout: Layout) -> bool {
+        return layout.size() <= self.block_size && layout.align() <= self.block_align;
+    }
+
+    fn new_page(&self) -> Result<(), AllocError> {
+        let ptr = unsafe { map_region(core::ptr::null(), self.page_size)? };
+        let ptr = NonNull::new(ptr as *mut u8).ok_or(AllocError)?;
+
+        let mut pages = self.pages.replace(Pod::new());
+        pages.push(Page {
+            ptr,
+            size: self.page_size,
+        });
+        self.pages.replace(pages);
+
+        let begin = ptr.as_ptr() as usize;
+        let first = (begin + self.block_align - 1) & !(self.block_align - 1);
+        self.next.set(first);
+        self.end.set(begin + self.page_size);
+
+        return Ok(());
+    }
+
+    fn take_block(&self) -> Result<NonNull<u8>, AllocError> {
+        if let Some(block) = self.free.get() {
+            self.free.set(unsafe { block.as_ref().next });
+
+            return Ok(block.cast());
+        }
+
+        if self.next.get() + self.block_size > self.end.get() {
+            self.new_page()?;
+        }
+
+        let block = self.next.get();
+        self.next.set(block + self.block_size);
+
+        return Ok(unsafe { NonNull::new_unchecked(block as *mut u8) });
+    }
+
+    #[inline(always)]
+    fn block_slice(&self, ptr: NonNull<u8>) -> NonNull<[u8]> {
+        let slice = core::ptr::slice_from_raw_parts_mut(ptr.as_ptr(), self.block_size);
+
+        return unsafe { NonNull::new_unchecked(slice) };
+    }
+}
+
+impl Drop for Pool {
+    fn drop(&mut self) {
+        let pages = self.pages.replace(Pod::new());
+
+        for page in pages {
+            unsafe {
+                let _ = unmap_region(page.ptr.as_ptr() as *const (), page.size);
+            }
+        }
+    }
+}
+
+unsafe impl Allocator for Pool {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        if !self.fits(layout) {
+            return Err(AllocError);
+        }
+
+        let block = self.take_block()?;
+        self.live.set(self.live.get() + 1);
+
+        return Ok(self.block_slice(block));
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        let mut block = ptr.cast::<FreeBlock>();
+        block.as_mut().next = self.free.get();
+
+        self.free.set(Some(block));
+        self.live.set(self.live.get() - 1);
+    }
+
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        // Blocks are all aligned to `block_align`, so this is all we need
+        if !self.fits(new_layout) {
+            return Err(AllocError);
+        }
+
+        return Ok(self.block_slice(ptr));
+    }
+
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let block = self.grow(ptr, old_layout, new_layout)?;
+
+        let tail = ptr.as_ptr().add(old_layout.size());
+        tail.write_bytes(0, self.block_size - old_layout.size());
+
+        return Ok(block);
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        if !self.fits(new_layout) {
+            return Err(AllocError);
+        }
+
+        return Ok(self.block_slice(ptr));
+    }
+
+    fn owns(&self, ptr: NonNull<u8>) -> bool {
+        let pages = self.pages.replace(Pod::new());
+
+        let addr = ptr.as_ptr() as usize;
+        let owns = pages.iter().any(|page| {
+            let begin = page.ptr.as_ptr() as usize;
+
+            begin <= addr && addr < begin + page.size
+        });
+
+        self.pages.replace(pages);
+
+        return owns;
+    }
+}
+
+impl AllocStat for Pool {
+    fn total_used(&self) -> usize {
+        return self.live.get() * self.block_size;
+    }
+
+    fn total_capacity(&self) -> usize {
+        let pages = self.pages.replace(Pod::new());
+        let capacity = pages.len() * self.page_size;
+        self.pages.replace(pages);
+
+        return capacity;
+    }
+}
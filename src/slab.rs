@@ -0,0 +1,368 @@
+use crate::alloc_api::*;
+use crate::global_bulk::*;
+use crate::pod::*;
+use alloc::alloc::Layout;
+use core::cell::Cell;
+use core::ptr::NonNull;
+
+const PAGE_SIZE: usize = 4096;
+const MIN_CLASS_SHIFT: u32 = 4;
+const CLASS_COUNT: usize = 9;
+
+struct FreeBlock {
+    next: Option<NonNull<FreeBlock>>,
+}
+
+struct SizeClass {
+    free: Cell<Option<NonNull<FreeBlock>>>,
+    next: Cell<usize>,
+    end: Cell<usize>,
+}
+
+#[derive(Clone, Copy)]
+struct Mapping {
+    base: NonNull<u8>,
+    mapped: usize,
+    ptr: NonNull<u8>,
+}
+
+#[inline(always)]
+fn round_up(size: usize, align: usize) -> usize {
+    return (size + align - 1) & !(align - 1);
+}
+
+#[inline(always)]
+fn sized_slice(ptr: NonNull<u8>, size: usize) -> NonNull<[u8]> {
+    let slice = core::ptr::slice_from_raw_parts_mut(ptr.as_ptr(), size);
+
+    return unsafe { NonNull::new_unchecked(slice) };
+}
+
+/// General purpose single-threaded allocator. Requests of up to
+/// `Slab::MAX_BLOCK` bytes are rounded up to a power of two and served from
+/// per-size-class slabs carved out of `map_region` pages; anything bigger gets
+/// its own mapping, which is unmapped as soon as it's freed.
+///
+/// Slabs are only given back when the allocator is dropped. `total_used` is
+/// the number of bytes actually requested, and `total_capacity` is everything
+/// that's mapped, so the difference between the two is the fragmentation.
+pub struct Slab {
+    classes: [SizeClass; CLASS_COUNT],
+    slabs: Cell<Pod<NonNull<u8>>>,
+    large: Cell<Pod<Mapping>>,
+    used: Cell<usize>,
+    capacity: Cell<usize>,
+}
+
+impl Slab {
+    pub const MIN_BLOCK: usize = 1 << MIN_CLASS_SHIFT;
+    pub const MAX_BLOCK: usize = Self::MIN_BLOCK << (CLASS_COUNT - 1);
+    pub const SLAB_SIZE: usize = 64 * 1024;
+
+    pub fn new() -> Self {
+        return Self {
+            classes: core::array::from_fn(|_| SizeClass {
+                free: Cell::new(None),
+                next: Cell::new(0),
+                end: Cell::new(0),
+            }),
+            slabs: Cell::new(Pod::new()),
+            large: Cell::new(Pod::new()),
+            used: Cell::new(0),
+            capacity: Cell::new(0),
+        };
+    }
+
+    /// The size class a layout is served from, or `None` if it gets its own
+    /// mapping.
+    #[inline(always)]
+    fn class_of(layout: Layout) -> Option<usize> {
+        let size = core::cmp::max(layout.size(), layout.align());
+        let size = core::cmp::max(size, Self::MIN_BLOCK);
+
+        if size > Self::MAX_BLOCK {
+            return None;
+        }
+
+        let shift = size.next_power_of_two().trailing_zeros();
+        return Some((shift - MIN_CLASS_SHIFT) as usize);
+    }
+
+    #[inline(always)]
+    fn class_size(class: usize) -> usize {
+        return Self::MIN_BLOCK << class;
+    }
+
+    fn new_slab(&self, class: usize) -> Result<(), AllocError> {
+        let ptr = unsafe { map_region(core::ptr::null(), Self::SLAB_SIZE)? };
+        let ptr = NonNull::new(ptr as *mut u8).ok_or(AllocError)?;
+
+        let mut slabs = self.slabs.replace(Pod::new());
+        slabs.push(ptr);
+        self.slabs.replace(slabs);
+
+        // Slabs are page aligned, so every block is aligned to its size
+        let begin = ptr.as_ptr() as usize;
+        let class = &self.classes[class];
+        class.next.set(begin);
+        class.end.set(begin + Self::SLAB_SIZE);
+
+        self.capacity.set(self.capacity.get() + Self::SLAB_SIZE);
+
+        return Ok(());
+    }
+
+    fn take_block(&self, class: usize) -> Result<NonNull<u8>, AllocError> {
+        let size_class = &self.classes[class];
+        if let Some(block) = size_class.free.get() {
+            size_class.free.set(unsafe { block.as_ref().next });
+
+            return Ok(block.cast());
+        }
+
+        let block_size = Self::class_size(class);
+        if size_class.next.get() + block_size > size_class.end.get() {
+            self.new_slab(class)?;
+        }
+
+        let block = size_class.next.get();
+        size_class.next.set(block + block_size);
+
+        return Ok(unsafe { NonNull::new_unchecked(block as *mut u8) });
+    }
+
+    unsafe fn give_block(&self, class: usize, ptr: NonNull<u8>) {
+        let size_class = &self.classes[class];
+
+        let mut block = ptr.cast::<FreeBlock>();
+        block.as_mut().next = size_class.free.get();
+        size_class.free.set(Some(block));
+    }
+
+    fn map_large(&self, layout: Layout) -> Result<NonNull<u8>, AllocError> {
+        // mmap only guarantees page alignment, so bigger alignments need slack
+        let padding = match layout.align() > PAGE_SIZE {
+            true => layout.align(),
+            false => 0,
+        };
+
+        let size = layout.size().checked_add(padding).ok_or(AllocError)?;
+        if size > isize::MAX as usize - PAGE_SIZE {
+            return Err(AllocError);
+        }
+
+        let mapped = round_up(size, PAGE_SIZE);
+        let base = unsafe { map_region(core::ptr::null(), mapped)? };
+        let base = NonNull::new(base as *mut u8).ok_or(AllocError)?;
+
+        let addr = round_up(base.as_ptr() as usize, layout.align());
+        let ptr = unsafe { NonNull::new_unchecked(addr as *mut u8) };
+
+        let mut large = self.large.replace(Pod::new());
+        large.push(Mapping { base, mapped, ptr });
+        self.large.replace(large);
+
+        self.capacity.set(self.capacity.get() + mapped);
+
+        return Ok(ptr);
+    }
+
+    fn find_large(&self, ptr: NonNull<u8>) -> Option<(usize, Mapping)> {
+        let large = self.large.replace(Pod::new());
+        let found = large.iter().position(|m| m.ptr == ptr);
+        let found = found.map(|index| (index, large[index]));
+        self.large.replace(large);
+
+        return found;
+    }
+
+    unsafe fn unmap_large(&self, ptr: NonNull<u8>) {
+        let (index, mapping) = match self.find_large(ptr) {
+            Some(found) => found,
+            None => panic!("freed a large block that this slab never mapped"),
+        };
+
+        let mut large = self.large.replace(Pod::new());
+        large.remove(index);
+        self.large.replace(large);
+
+        self.capacity.set(self.capacity.get() - mapping.mapped);
+        let _ = unmap_region(mapping.base.as_ptr() as *const (), mapping.mapped);
+    }
+
+    fn allocate_impl(&self, layout: Layout, zeroed: bool) -> Result<NonNull<[u8]>, AllocError> {
+        let ptr = match Self::class_of(layout) {
+            Some(class) => {
+                let ptr = self.take_block(class)?;
+                if zeroed {
+                    unsafe { ptr.as_ptr().write_bytes(0, layout.size()) };
+                }
+
+                ptr
+            }
+
+            // Fresh mappings are already zeroed
+            None => self.map_large(layout)?,
+        };
+
+        self.used.set(self.used.get() + layout.size());
+
+        return Ok(sized_slice(ptr, layout.size()));
+    }
+
+    // Whether the block at `ptr` can be resized to `new_layout` without
+    // moving it.
+    fn fits_in_place(&self, ptr: NonNull<u8>, old_layout: Layout, new_layout: Layout) -> bool {
+        let addr = ptr.as_ptr() as usize;
+        if !addr.is_multiple_of(new_layout.align()) {
+            return false;
+        }
+
+        let old_class = Self::class_of(old_layout);
+        if old_class.is_some() {
+            return old_class == Self::class_of(new_layout);
+        }
+
+        if Self::class_of(new_layout).is_some() {
+            // Moving into a slab gives the whole mapping back
+            return false;
+        }
+
+        let mapping = match self.find_large(ptr) {
+            Some((_, mapping)) => mapping,
+            None => return false,
+        };
+
+        let end = mapping.base.as_ptr() as usize + mapping.mapped;
+        return addr + new_layout.size() <= end;
+    }
+
+    unsafe fn resize(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+        zeroed: bool,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let (old_size, new_size) = (old_layout.size(), new_layout.size());
+
+        if self.fits_in_place(ptr, old_layout, new_layout) {
+            if zeroed && new_size > old_size {
+                let tail = ptr.as_ptr().add(old_size);
+                tail.write_bytes(0, new_size - old_size);
+            }
+
+            self.used.set(self.used.get() - old_size + new_size);
+
+            return Ok(sized_slice(ptr, new_size));
+        }
+
+        let new_ptr = self.allocate_impl(new_layout, zeroed)?;
+
+        let size = core::cmp::min(old_size, new_size);
+        core::ptr::copy_nonoverlapping(ptr.as_ptr(), new_ptr.as_ptr() as *mut u8, size);
+        self.deallocate(ptr, old_layout);
+
+        return Ok(new_ptr);
+    }
+}
+
+impl Drop for Slab {
+    fn drop(&mut self) {
+        let slabs = self.slabs.replace(Pod::new());
+        for slab in slabs {
+            unsafe {
+                let _ = unmap_region(slab.as_ptr() as *const (), Self::SLAB_SIZE);
+            }
+        }
+
+        let large = self.large.replace(Pod::new());
+        for mapping in large {
+            unsafe {
+                let _ = unmap_region(mapping.base.as_ptr() as *const (), mapping.mapped);
+            }
+        }
+    }
+}
+
+unsafe impl Allocator for Slab {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        return self.allocate_impl(layout, false);
+    }
+
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        return self.allocate_impl(layout, true);
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        match Self::class_of(layout) {
+            Some(class) => self.give_block(class, ptr),
+            None => self.unmap_large(ptr),
+        }
+
+        self.used.set(self.used.get() - layout.size());
+    }
+
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return self.resize(ptr, old_layout, new_layout, false);
+    }
+
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return self.resize(ptr, old_layout, new_layout, true);
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return self.resize(ptr, old_layout, new_layout, false);
+    }
+
+    fn owns(&self, ptr: NonNull<u8>) -> bool {
+        let addr = ptr.as_ptr() as usize;
+
+        let slabs = self.slabs.replace(Pod::new());
+        let in_slab = slabs.iter().any(|slab| {
+            let begin = slab.as_ptr() as usize;
+
+            begin <= addr && addr < begin + Self::SLAB_SIZE
+        });
+        self.slabs.replace(slabs);
+
+        if in_slab {
+            return true;
+        }
+
+        let large = self.large.replace(Pod::new());
+        let in_large = large.iter().any(|mapping| {
+            let begin = mapping.base.as_ptr() as usize;
+
+            begin <= addr && addr < begin + mapping.mapped
+        });
+        self.large.replace(large);
+
+        return in_large;
+    }
+}
+
+impl AllocStat for Slab {
+    fn total_used(&self) -> usize {
+        return self.used.get();
+    }
+
+    fn total_capacity(&self) -> usize {
+        return self.capacity.get();
+    }
+}
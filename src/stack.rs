@@ -0,0 +1,431 @@
+use crate::alloc_api::*;
+use crate::global_bulk::*;
+use crate::pod::*;
+use alloc::alloc::Layout;
+use core::cell::Cell;
+use core::ptr::NonNull;
+
+#[inline(always)]
+pub(crate) fn sized_slice(ptr: NonNull<u8>, size: usize) -> NonNull<[u8]> {
+    let slice = core::ptr::slice_from_raw_parts_mut(ptr.as_ptr(), size);
+
+    return unsafe { NonNull::new_unchecked(slice) };
+}
+
+// The bump-with-pop logic shared by all the allocators that work on a single
+// contiguous region. Blocks are packed with no headers, so only the topmost
+// block can be given back. Everything is kept as offsets from the start of the
+// region, so that regions stored inline can move between allocations.
+pub(crate) struct StackCore {
+    capacity: usize,
+    top: Cell<usize>,
+}
+
+impl StackCore {
+    pub(crate) const fn new(capacity: usize) -> Self {
+        return Self {
+            capacity,
+            top: Cell::new(0),
+        };
+    }
+
+    #[inline(always)]
+    pub(crate) fn top(&self) -> usize {
+        return self.top.get();
+    }
+
+    #[inline(always)]
+    pub(crate) fn set_top(&self, top: usize) {
+        debug_assert!(top <= self.capacity);
+
+        self.top.set(top);
+    }
+
+    #[inline(always)]
+    fn is_top(&self, base: usize, ptr: NonNull<u8>, size: usize) -> bool {
+        return ptr.as_ptr() as usize + size == base + self.top.get();
+    }
+
+    pub(crate) fn alloc(&self, base: usize, layout: Layout) -> Option<NonNull<u8>> {
+        let align_mask = layout.align() - 1;
+        let begin = (base + self.top.get()).checked_add(align_mask)? & !align_mask;
+        let end = begin.checked_add(layout.size())?;
+
+        if end - base > self.capacity {
+            return None;
+        }
+
+        self.top.set(end - base);
+
+        return NonNull::new(begin as *mut u8);
+    }
+
+    /// Gives the block back if it's on top; returns whether it was.
+    pub(crate) fn pop(&self, base: usize, ptr: NonNull<u8>, size: usize) -> bool {
+        if !self.is_top(base, ptr, size) {
+            return false;
+        }
+
+        self.top.set(ptr.as_ptr() as usize - base);
+
+        return true;
+    }
+
+    /// Resizes the topmost block without moving it, if there's room.
+    fn resize(&self, base: usize, ptr: NonNull<u8>, old_size: usize, new_size: usize) -> bool {
+        if !self.is_top(base, ptr, old_size) {
+            return false;
+        }
+
+        let offset = ptr.as_ptr() as usize - base;
+        if new_size > self.capacity - offset {
+            return false;
+        }
+
+        self.top.set(offset + new_size);
+
+        return true;
+    }
+
+    /// Grows in place if the block is on top, and otherwise copies it to the
+    /// top; the old block stays allocated until it's rewound.
+    pub(crate) unsafe fn grow(
+        &self,
+        base: usize,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+        zeroed: bool,
+    ) -> Result<NonNull<u8>, AllocError> {
+        let (old_size, new_size) = (old_layout.size(), new_layout.size());
+        let aligned = (ptr.as_ptr() as usize).is_multiple_of(new_layout.align());
+
+        let new_ptr = match aligned && self.resize(base, ptr, old_size, new_size) {
+            true => ptr,
+            false => {
+                let new_ptr = self.alloc(base, new_layout).ok_or(AllocError)?;
+                let size = core::cmp::min(old_size, new_size);
+                core::ptr::copy_nonoverlapping(ptr.as_ptr(), new_ptr.as_ptr(), size);
+
+                new_ptr
+            }
+        };
+
+        if zeroed && new_size > old_size {
+            let tail = new_ptr.as_ptr().add(old_size);
+            tail.write_bytes(0, new_size - old_size);
+        }
+
+        return Ok(new_ptr);
+    }
+
+    pub(crate) unsafe fn shrink(
+        &self,
+        base: usize,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<u8>, AllocError> {
+        if !(ptr.as_ptr() as usize).is_multiple_of(new_layout.align()) {
+            return self.grow(base, ptr, old_layout, new_layout, false);
+        }
+
+        // Blocks below the top keep their memory until they're rewound
+        self.resize(base, ptr, old_layout.size(), new_layout.size());
+
+        return Ok(ptr);
+    }
+
+    #[inline(always)]
+    pub(crate) fn owns(&self, base: usize, ptr: NonNull<u8>) -> bool {
+        let addr = ptr.as_ptr() as usize;
+
+        return base <= addr && addr < base + self.top.get();
+    }
+
+    #[inline(always)]
+    pub(crate) fn used(&self) -> usize {
+        return self.top.get();
+    }
+
+    #[inline(always)]
+    pub(crate) fn capacity(&self) -> usize {
+        return self.capacity;
+    }
+}
+
+/// Allocates from a single mapped region in LIFO order. Freeing the topmost
+/// block gives its memory back, and growing or shrinking it happens in place,
+/// so a `Pod` that's the last thing allocated never has to copy.
+///
+/// Blocks that aren't on top are only reclaimed when the stack is rewound
+/// with `set` or a `ScopedStack`; debug builds panic on out-of-order frees.
+pub struct StackAllocator {
+    base: usize,
+    core: StackCore,
+
+    // Start of every live block, bottom to top
+    #[cfg(debug_assertions)]
+    blocks: Cell<Pod<usize>>,
+}
+
+#[derive(Clone, Copy)]
+pub struct StackMark {
+    top: usize,
+}
+
+impl StackAllocator {
+    pub const DEFAULT_SIZE: usize = 16 * 1024 * 1024;
+
+    pub fn new() -> Self {
+        return Self::with_capacity(Self::DEFAULT_SIZE);
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        let ptr = match unsafe { map_region(core::ptr::null(), capacity) } {
+            Ok(ptr) => ptr as usize,
+            Err(e) => panic!("failed to map region"),
+        };
+
+        return Self {
+            base: ptr,
+            core: StackCore::new(capacity),
+
+            #[cfg(debug_assertions)]
+            blocks: Cell::new(Pod::new()),
+        };
+    }
+
+    pub fn save(&self) -> StackMark {
+        return StackMark {
+            top: self.core.top(),
+        };
+    }
+
+    pub unsafe fn set(&mut self, mark: StackMark) {
+        self.core.set_top(mark.top);
+
+        #[cfg(debug_assertions)]
+        {
+            let mut blocks = self.blocks.replace(Pod::new());
+            let top = self.base + mark.top;
+            let live = blocks.iter().take_while(|&&b| b < top).count();
+            blocks.truncate(live);
+            self.blocks.replace(blocks);
+        }
+    }
+
+    pub fn scoped<'a>(&'a mut self) -> ScopedStack<'a> {
+        let mark = self.save();
+
+        return ScopedStack { mark, alloc: self };
+    }
+
+    #[inline(always)]
+    fn push_block(&self, ptr: NonNull<u8>) {
+        #[cfg(debug_assertions)]
+        {
+            let mut blocks = self.blocks.replace(Pod::new());
+            blocks.push(ptr.as_ptr() as usize);
+            self.blocks.replace(blocks);
+        }
+    }
+
+    // Forgets about a block without giving back its memory, for blocks that
+    // were moved by a resize.
+    #[inline(always)]
+    fn drop_block(&self, ptr: NonNull<u8>) {
+        #[cfg(debug_assertions)]
+        {
+            let mut blocks = self.blocks.replace(Pod::new());
+            let addr = ptr.as_ptr() as usize;
+            if let Some(index) = blocks.iter().rposition(|&b| b == addr) {
+                blocks.remove(index);
+            }
+
+            self.blocks.replace(blocks);
+        }
+    }
+
+    unsafe fn grow_impl(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+        zeroed: bool,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let new_ptr = self
+            .core
+            .grow(self.base, ptr, old_layout, new_layout, zeroed)?;
+
+        if new_ptr != ptr {
+            self.drop_block(ptr);
+            self.push_block(new_ptr);
+        }
+
+        return Ok(sized_slice(new_ptr, new_layout.size()));
+    }
+}
+
+impl Drop for StackAllocator {
+    fn drop(&mut self) {
+        let capacity = self.core.capacity();
+
+        unsafe {
+            let _ = unmap_region(self.base as *const (), capacity);
+        }
+    }
+}
+
+unsafe impl Allocator for StackAllocator {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let ptr = self.core.alloc(self.base, layout).ok_or(AllocError)?;
+        self.push_block(ptr);
+
+        return Ok(sized_slice(ptr, layout.size()));
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        #[cfg(debug_assertions)]
+        {
+            let mut blocks = self.blocks.replace(Pod::new());
+            let on_top = blocks.last() == Some(&(ptr.as_ptr() as usize));
+            if on_top {
+                blocks.pop();
+            }
+
+            self.blocks.replace(blocks);
+
+            if !on_top {
+                panic!("out-of-order free: block isn't on top of the stack");
+            }
+        }
+
+        self.core.pop(self.base, ptr, layout.size());
+    }
+
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return self.grow_impl(ptr, old_layout, new_layout, false);
+    }
+
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return self.grow_impl(ptr, old_layout, new_layout, true);
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let new_ptr = self.core.shrink(self.base, ptr, old_layout, new_layout)?;
+
+        if new_ptr != ptr {
+            self.drop_block(ptr);
+            self.push_block(new_ptr);
+        }
+
+        return Ok(sized_slice(new_ptr, new_layout.size()));
+    }
+
+    fn owns(&self, ptr: NonNull<u8>) -> bool {
+        return self.core.owns(self.base, ptr);
+    }
+}
+
+impl AllocStat for StackAllocator {
+    fn total_used(&self) -> usize {
+        return self.core.used();
+    }
+
+    fn total_capacity(&self) -> usize {
+        return self.core.capacity();
+    }
+}
+
+pub struct ScopedStack<'a> {
+    mark: StackMark,
+    alloc: &'a mut StackAllocator,
+}
+
+impl<'a> ScopedStack<'a> {
+    pub fn chain<'b>(&'b mut self) -> ScopedStack<'b> {
+        let mark = self.alloc.save();
+
+        return ScopedStack {
+            mark,
+            alloc: &mut self.alloc,
+        };
+    }
+}
+
+impl<'a> Drop for ScopedStack<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            self.alloc.set(self.mark);
+        }
+    }
+}
+
+unsafe impl<'a> Allocator for ScopedStack<'a> {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        return self.alloc.allocate(layout);
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.alloc.deallocate(ptr, layout);
+    }
+
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return self.alloc.grow(ptr, old_layout, new_layout);
+    }
+
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return self.alloc.grow_zeroed(ptr, old_layout, new_layout);
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return self.alloc.shrink(ptr, old_layout, new_layout);
+    }
+
+    fn owns(&self, ptr: NonNull<u8>) -> bool {
+        let addr = ptr.as_ptr() as usize;
+
+        return self.alloc.base + self.mark.top <= addr && self.alloc.owns(ptr);
+    }
+}
+
+impl AllocStat for ScopedStack<'_> {
+    fn total_used(&self) -> usize {
+        return self.alloc.core.top() - self.mark.top;
+    }
+
+    fn total_capacity(&self) -> usize {
+        return self.alloc.core.capacity() - self.mark.top;
+    }
+}
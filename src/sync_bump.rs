@@ -0,0 +1,201 @@
+use crate::alloc_api::*;
+use alloc::alloc::{alloc, dealloc, Layout};
+use core::ptr::{self, NonNull};
+use core::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+// Lives at the start of the memory it describes, so pushing a bucket never
+// needs to allocate anywhere else.
+struct SyncBucket {
+    prev: *mut SyncBucket,
+    layout: Layout,
+    end: usize,
+    current: AtomicUsize,
+}
+
+const HEADER_SIZE: usize = core::mem::size_of::<SyncBucket>();
+
+impl SyncBucket {
+    fn new(prev: *mut SyncBucket, capacity: usize) -> Result<NonNull<SyncBucket>, AllocError> {
+        let size = capacity.checked_add(HEADER_SIZE).ok_or(AllocError)?;
+        let layout = Layout::from_size_align(size, core::mem::align_of::<SyncBucket>());
+        let layout = layout.map_err(|_| AllocError)?;
+
+        let ptr = unsafe { alloc(layout) } as *mut SyncBucket;
+        let ptr = NonNull::new(ptr).ok_or(AllocError)?;
+
+        let begin = ptr.as_ptr() as usize + HEADER_SIZE;
+        let bucket = SyncBucket {
+            prev,
+            layout,
+            end: begin + capacity,
+            current: AtomicUsize::new(begin),
+        };
+
+        unsafe { ptr.as_ptr().write(bucket) };
+
+        return Ok(ptr);
+    }
+
+    #[inline(always)]
+    fn begin(&self) -> usize {
+        return self as *const SyncBucket as usize + HEADER_SIZE;
+    }
+
+    // Returns the address of the allocation and how far `current` moved.
+    #[inline(always)]
+    fn alloc(&self, layout: Layout) -> Option<(usize, usize)> {
+        let mut current = self.current.load(Ordering::Relaxed);
+
+        loop {
+            let align_mask = layout.align() - 1;
+            let begin = current.checked_add(align_mask)? & !align_mask;
+            let end = begin.checked_add(layout.size())?;
+
+            if end > self.end {
+                return None;
+            }
+
+            let relaxed = Ordering::Relaxed;
+            let result = self
+                .current
+                .compare_exchange_weak(current, end, relaxed, relaxed);
+            match result {
+                Ok(_) => return Some((begin, end - current)),
+                Err(actual) => current = actual,
+            }
+        }
+    }
+}
+
+/// Like `BucketList`, but can be shared between threads. Allocating is a
+/// compare-exchange on the current bucket; only pushing a new bucket takes a
+/// lock.
+pub struct SyncBucketList {
+    head: AtomicPtr<SyncBucket>,
+    lock: Mutex<()>,
+    used: AtomicUsize,
+    capacity: AtomicUsize,
+}
+
+impl SyncBucketList {
+    pub const DEFAULT_BUCKET_SIZE: usize = 2 * 1024 * 1024;
+
+    #[inline(always)]
+    pub fn new() -> Self {
+        return Self {
+            head: AtomicPtr::new(ptr::null_mut()),
+            lock: Mutex::new(()),
+            used: AtomicUsize::new(0),
+            capacity: AtomicUsize::new(0),
+        };
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        let bucket = match SyncBucket::new(ptr::null_mut(), capacity) {
+            Ok(bucket) => bucket,
+            Err(e) => panic!("failed to allocate bucket"),
+        };
+
+        return Self {
+            head: AtomicPtr::new(bucket.as_ptr()),
+            lock: Mutex::new(()),
+            used: AtomicUsize::new(0),
+            capacity: AtomicUsize::new(capacity),
+        };
+    }
+
+    #[inline(always)]
+    fn try_head(&self, layout: Layout) -> Option<usize> {
+        let head = self.head.load(Ordering::Acquire);
+        let head = unsafe { head.as_ref()? };
+
+        let (ptr, used) = head.alloc(layout)?;
+        self.used.fetch_add(used, Ordering::Relaxed);
+
+        return Some(ptr);
+    }
+
+    #[cold]
+    fn alloc_slow(&self, layout: Layout) -> Result<usize, AllocError> {
+        let guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+
+        // Someone else might've pushed a bucket while we were waiting
+        if let Some(ptr) = self.try_head(layout) {
+            return Ok(ptr);
+        }
+
+        let padded = layout.size().checked_add(layout.align() - 1);
+        let padded = padded.ok_or(AllocError)?;
+        let capacity = core::cmp::max(padded, Self::DEFAULT_BUCKET_SIZE);
+
+        let head = self.head.load(Ordering::Acquire);
+        let bucket = SyncBucket::new(head, capacity)?;
+        let (ptr, used) = match unsafe { bucket.as_ref().alloc(layout) } {
+            Some(result) => result,
+            None => panic!("fresh bucket is too small"),
+        };
+
+        self.used.fetch_add(used, Ordering::Relaxed);
+        self.capacity.fetch_add(capacity, Ordering::Relaxed);
+        self.head.store(bucket.as_ptr(), Ordering::Release);
+
+        return Ok(ptr);
+    }
+}
+
+impl Drop for SyncBucketList {
+    fn drop(&mut self) {
+        let mut bucket = *self.head.get_mut();
+
+        while !bucket.is_null() {
+            unsafe {
+                let SyncBucket { prev, layout, .. } = bucket.read();
+                dealloc(bucket as *mut u8, layout);
+
+                bucket = prev;
+            }
+        }
+    }
+}
+
+unsafe impl Allocator for SyncBucketList {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let ptr = match self.try_head(layout) {
+            Some(ptr) => ptr,
+            None => self.alloc_slow(layout)?,
+        };
+
+        let slice = ptr::slice_from_raw_parts_mut(ptr as *mut u8, layout.size());
+        return NonNull::new(slice).ok_or(AllocError);
+    }
+
+    // deallocation doesn't do anything
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {}
+
+    fn owns(&self, ptr: NonNull<u8>) -> bool {
+        let addr = ptr.as_ptr() as usize;
+        let mut bucket = self.head.load(Ordering::Acquire);
+
+        while let Some(b) = unsafe { bucket.as_ref() } {
+            let current = b.current.load(Ordering::Relaxed);
+            if b.begin() <= addr && addr < current {
+                return true;
+            }
+
+            bucket = b.prev;
+        }
+
+        return false;
+    }
+}
+
+impl AllocStat for SyncBucketList {
+    fn total_used(&self) -> usize {
+        return self.used.load(Ordering::Relaxed);
+    }
+
+    fn total_capacity(&self) -> usize {
+        return self.capacity.load(Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,617 @@
+use crate::alloc_api::*;
+use crate::global_bulk::*;
+use crate::pod::*;
+use alloc::alloc::Layout;
+use core::cell::{Cell, UnsafeCell};
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+use core::ptr::{self, NonNull};
+
+const WORD: usize = core::mem::size_of::<usize>();
+const ALIGN: usize = 2 * WORD;
+const ALIGN_LOG2: u32 = ALIGN.trailing_zeros();
+
+const SL_LOG2: u32 = 4;
+const SL_COUNT: usize = 1 << SL_LOG2;
+const FL_SHIFT: u32 = SL_LOG2 + ALIGN_LOG2;
+const FL_COUNT: usize = (usize::BITS - FL_SHIFT + 1) as usize;
+const SMALL_BLOCK: usize = 1 << FL_SHIFT;
+
+// `prev_phys` and `size`; the free list links live in the payload
+const HEADER: usize = 2 * WORD;
+const MIN_BLOCK: usize = 2 * WORD;
+const MAX_BLOCK: usize = 1 << (usize::BITS - 2);
+
+const FREE_BIT: usize = 1;
+const PREV_FREE_BIT: usize = 2;
+const FLAG_BITS: usize = FREE_BIT | PREV_FREE_BIT;
+
+const PAGE_SIZE: usize = 4096;
+
+#[repr(C)]
+struct Block {
+    // Only valid while the previous block is free
+    prev_phys: *mut Block,
+    size: usize,
+
+    // Only valid while this block is free
+    next_free: *mut Block,
+    prev_free: *mut Block,
+}
+
+#[inline(always)]
+unsafe fn block_size(block: *mut Block) -> usize {
+    return (*block).size & !FLAG_BITS;
+}
+
+#[inline(always)]
+unsafe fn set_size(block: *mut Block, size: usize) {
+    (*block).size = size | ((*block).size & FLAG_BITS);
+}
+
+#[inline(always)]
+unsafe fn is_free(block: *mut Block) -> bool {
+    return (*block).size & FREE_BIT != 0;
+}
+
+#[inline(always)]
+unsafe fn is_prev_free(block: *mut Block) -> bool {
+    return (*block).size & PREV_FREE_BIT != 0;
+}
+
+#[inline(always)]
+unsafe fn set_flag(block: *mut Block, flag: usize, value: bool) {
+    match value {
+        true => (*block).size |= flag,
+        false => (*block).size &= !flag,
+    }
+}
+
+#[inline(always)]
+unsafe fn payload(block: *mut Block) -> *mut u8 {
+    return (block as *mut u8).add(HEADER);
+}
+
+#[inline(always)]
+unsafe fn from_payload(ptr: *mut u8) -> *mut Block {
+    return ptr.sub(HEADER) as *mut Block;
+}
+
+#[inline(always)]
+unsafe fn next_phys(block: *mut Block) -> *mut Block {
+    return payload(block).add(block_size(block)) as *mut Block;
+}
+
+#[inline(always)]
+unsafe fn link_next(block: *mut Block) -> *mut Block {
+    let next = next_phys(block);
+    (*next).prev_phys = block;
+
+    return next;
+}
+
+#[inline(always)]
+unsafe fn mark_free(block: *mut Block) {
+    let next = link_next(block);
+    set_flag(next, PREV_FREE_BIT, true);
+    set_flag(block, FREE_BIT, true);
+}
+
+#[inline(always)]
+unsafe fn mark_used(block: *mut Block) {
+    let next = next_phys(block);
+    set_flag(next, PREV_FREE_BIT, false);
+    set_flag(block, FREE_BIT, false);
+}
+
+#[inline(always)]
+unsafe fn can_split(block: *mut Block, size: usize) -> bool {
+    return block_size(block) >= size + HEADER + MIN_BLOCK;
+}
+
+// Splits off everything after the first `size` bytes of payload into a new
+// free block, and returns it.
+unsafe fn split(block: *mut Block, size: usize) -> *mut Block {
+    let remaining = payload(block).add(size) as *mut Block;
+    (*remaining).size = block_size(block) - (size + HEADER);
+    set_size(block, size);
+    mark_free(remaining);
+
+    return remaining;
+}
+
+#[inline(always)]
+fn fls(x: usize) -> u32 {
+    return usize::BITS - 1 - x.leading_zeros();
+}
+
+#[inline(always)]
+fn round_up(size: usize, align: usize) -> usize {
+    return (size + align - 1) & !(align - 1);
+}
+
+#[inline(always)]
+fn adjust_request(size: usize) -> Option<usize> {
+    if size > MAX_BLOCK {
+        return None;
+    }
+
+    return Some(core::cmp::max(round_up(size, ALIGN), MIN_BLOCK));
+}
+
+#[inline(always)]
+fn mapping_insert(size: usize) -> (usize, usize) {
+    if size < SMALL_BLOCK {
+        return (0, size / (SMALL_BLOCK / SL_COUNT));
+    }
+
+    let fl = fls(size);
+    let sl = (size >> (fl - SL_LOG2)) ^ SL_COUNT;
+
+    return ((fl - (FL_SHIFT - 1)) as usize, sl);
+}
+
+// Rounds up to the next list, so that any block in the list we end up in is
+// big enough.
+#[inline(always)]
+fn mapping_search(size: usize) -> (usize, usize) {
+    if size < SMALL_BLOCK {
+        return mapping_insert(size);
+    }
+
+    let round = (1 << (fls(size) - SL_LOG2)) - 1;
+    return mapping_insert(size + round);
+}
+
+struct Control {
+    fl_bitmap: usize,
+    sl_bitmap: [u32; FL_COUNT],
+    blocks: [[*mut Block; SL_COUNT]; FL_COUNT],
+}
+
+impl Control {
+    unsafe fn insert(&mut self, block: *mut Block) {
+        let (fl, sl) = mapping_insert(block_size(block));
+        let head = self.blocks[fl][sl];
+
+        (*block).next_free = head;
+        (*block).prev_free = ptr::null_mut();
+        if !head.is_null() {
+            (*head).prev_free = block;
+        }
+
+        self.blocks[fl][sl] = block;
+        self.fl_bitmap |= 1 << fl;
+        self.sl_bitmap[fl] |= 1 << sl;
+    }
+
+    unsafe fn remove(&mut self, block: *mut Block) {
+        let (fl, sl) = mapping_insert(block_size(block));
+        let (prev, next) = ((*block).prev_free, (*block).next_free);
+
+        if !next.is_null() {
+            (*next).prev_free = prev;
+        }
+
+        if !prev.is_null() {
+            (*prev).next_free = next;
+            return;
+        }
+
+        self.blocks[fl][sl] = next;
+        if next.is_null() {
+            self.sl_bitmap[fl] &= !(1 << sl);
+
+            if self.sl_bitmap[fl] == 0 {
+                self.fl_bitmap &= !(1 << fl);
+            }
+        }
+    }
+
+    // Finds and removes a free block of at least `size` bytes, with two bit
+    // scans and no loops.
+    unsafe fn take(&mut self, size: usize) -> Option<*mut Block> {
+        let (mut fl, sl) = mapping_search(size);
+        if fl >= FL_COUNT {
+            return None;
+        }
+
+        let mut sl_map = self.sl_bitmap[fl] & (!0 << sl);
+        if sl_map == 0 {
+            let fl_map = self.fl_bitmap & (!0usize).checked_shl(fl as u32 + 1).unwrap_or(0);
+            if fl_map == 0 {
+                return None;
+            }
+
+            fl = fl_map.trailing_zeros() as usize;
+            sl_map = self.sl_bitmap[fl];
+        }
+
+        let sl = sl_map.trailing_zeros() as usize;
+        let block = self.blocks[fl][sl];
+        self.remove(block);
+
+        return Some(block);
+    }
+
+    unsafe fn absorb(&mut self, prev: *mut Block, block: *mut Block) {
+        set_size(prev, block_size(prev) + block_size(block) + HEADER);
+        link_next(prev);
+    }
+
+    unsafe fn merge_prev(&mut self, block: *mut Block) -> *mut Block {
+        if !is_prev_free(block) {
+            return block;
+        }
+
+        let prev = (*block).prev_phys;
+        self.remove(prev);
+        self.absorb(prev, block);
+
+        return prev;
+    }
+
+    unsafe fn merge_next(&mut self, block: *mut Block) -> *mut Block {
+        let next = next_phys(block);
+        if is_free(next) {
+            self.remove(next);
+            self.absorb(block, next);
+        }
+
+        return block;
+    }
+
+    // Gives back the tail of a free block that's about to be used.
+    unsafe fn trim_free(&mut self, block: *mut Block, size: usize) {
+        if can_split(block, size) {
+            let remaining = split(block, size);
+            link_next(block);
+            set_flag(remaining, PREV_FREE_BIT, true);
+            self.insert(remaining);
+        }
+    }
+
+    // Gives back the tail of a block that's in use.
+    unsafe fn trim_used(&mut self, block: *mut Block, size: usize) {
+        if can_split(block, size) {
+            let remaining = split(block, size);
+            set_flag(remaining, PREV_FREE_BIT, false);
+
+            let remaining = self.merge_next(remaining);
+            self.insert(remaining);
+        }
+    }
+
+    // Gives back the first `gap` bytes of a free block, and returns the rest.
+    unsafe fn trim_free_leading(&mut self, block: *mut Block, gap: usize) -> *mut Block {
+        if !can_split(block, gap - HEADER) {
+            return block;
+        }
+
+        let remaining = split(block, gap - HEADER);
+        set_flag(remaining, PREV_FREE_BIT, true);
+        link_next(block);
+        self.insert(block);
+
+        return remaining;
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Region {
+    ptr: NonNull<u8>,
+    size: usize,
+    mapped: bool,
+}
+
+#[inline(always)]
+fn sized_slice(ptr: *mut u8, size: usize) -> NonNull<[u8]> {
+    let slice = ptr::slice_from_raw_parts_mut(ptr, size);
+
+    return unsafe { NonNull::new_unchecked(slice) };
+}
+
+/// Two-level segregated fit allocator. Allocating, freeing and resizing in
+/// place are all constant time: free blocks are kept in lists bucketed by
+/// size, and the right list is found with a couple of bit scans.
+///
+/// Memory comes from caller-provided buffers, or from `map_region`. Mapping a
+/// new pool is the one operation that isn't bounded, so allocators that need
+/// hard guarantees should use `from_buffer` or `with_capacity` up front.
+pub struct Tlsf<'a> {
+    control: UnsafeCell<Control>,
+    pools: Cell<Pod<Region>>,
+    can_map: bool,
+    used: Cell<usize>,
+    capacity: Cell<usize>,
+    buffers: PhantomData<&'a mut [MaybeUninit<u8>]>,
+}
+
+impl Tlsf<'static> {
+    /// Maps pools from `map_region` as they're needed.
+    pub fn new() -> Self {
+        return Self::empty(true);
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        let tlsf = Self::new();
+        if tlsf.map_pool(capacity).is_err() {
+            panic!("failed to map region");
+        }
+
+        return tlsf;
+    }
+}
+
+impl<'a> Tlsf<'a> {
+    pub const DEFAULT_POOL_SIZE: usize = 1024 * 1024;
+
+    /// Only ever allocates out of `buffer`, and any others added with
+    /// `add_buffer`.
+    pub fn from_buffer(buffer: &'a mut [MaybeUninit<u8>]) -> Self {
+        let tlsf = Self::empty(false);
+        tlsf.add_buffer(buffer);
+
+        return tlsf;
+    }
+
+    fn empty(can_map: bool) -> Self {
+        let control = Control {
+            fl_bitmap: 0,
+            sl_bitmap: [0; FL_COUNT],
+            blocks: [[ptr::null_mut(); SL_COUNT]; FL_COUNT],
+        };
+
+        return Self {
+            control: UnsafeCell::new(control),
+            pools: Cell::new(Pod::new()),
+            can_map,
+            used: Cell::new(0),
+            capacity: Cell::new(0),
+            buffers: PhantomData,
+        };
+    }
+
+    /// Buffers too small to hold a block are ignored.
+    pub fn add_buffer(&self, buffer: &'a mut [MaybeUninit<u8>]) {
+        let ptr = unsafe { NonNull::new_unchecked(buffer.as_mut_ptr() as *mut u8) };
+
+        unsafe { self.add_pool(ptr, buffer.len(), false) };
+    }
+
+    fn map_pool(&self, size: usize) -> Result<(), AllocError> {
+        let size = round_up(size.checked_add(PAGE_SIZE).ok_or(AllocError)?, PAGE_SIZE);
+        let ptr = unsafe { map_region(ptr::null(), size)? };
+        let ptr = NonNull::new(ptr as *mut u8).ok_or(AllocError)?;
+
+        unsafe { self.add_pool(ptr, size, true) };
+
+        return Ok(());
+    }
+
+    // Callers can't hold on to the reference across calls that also take it
+    #[allow(clippy::mut_from_ref)]
+    #[inline(always)]
+    unsafe fn control(&self) -> &mut Control {
+        return &mut *self.control.get();
+    }
+
+    unsafe fn add_pool(&self, ptr: NonNull<u8>, size: usize, mapped: bool) {
+        let begin = round_up(ptr.as_ptr() as usize, ALIGN);
+        let end = (ptr.as_ptr() as usize + size) & !(ALIGN - 1);
+
+        // Room for one block plus the sentinel at the end
+        if end < begin || end - begin < 2 * HEADER + MIN_BLOCK {
+            return;
+        }
+
+        let block = begin as *mut Block;
+        (*block).prev_phys = ptr::null_mut();
+        (*block).size = core::cmp::min(end - begin - 2 * HEADER, MAX_BLOCK);
+        mark_free(block);
+
+        let sentinel = next_phys(block);
+        (*sentinel).size = 0;
+        set_flag(sentinel, PREV_FREE_BIT, true);
+
+        self.control().insert(block);
+
+        let mut pools = self.pools.replace(Pod::new());
+        pools.push(Region { ptr, size, mapped });
+        self.pools.replace(pools);
+
+        self.capacity.set(self.capacity.get() + size);
+    }
+
+    fn take_block(&self, size: usize) -> Result<*mut Block, AllocError> {
+        if let Some(block) = unsafe { self.control().take(size) } {
+            return Ok(block);
+        }
+
+        if !self.can_map {
+            return Err(AllocError);
+        }
+
+        // Enough for any rounding `mapping_search` might do
+        let pool_size = size.checked_mul(2).ok_or(AllocError)? + 2 * HEADER;
+        let pool_size = core::cmp::max(pool_size, Self::DEFAULT_POOL_SIZE);
+        self.map_pool(pool_size)?;
+
+        return unsafe { self.control().take(size).ok_or(AllocError) };
+    }
+
+    fn allocate_impl(&self, layout: Layout) -> Result<*mut u8, AllocError> {
+        let size = adjust_request(layout.size()).ok_or(AllocError)?;
+        let align = layout.align();
+
+        // Over-aligned requests need space for a free block in front
+        let gap_min = HEADER + MIN_BLOCK;
+        let search_size = match align > ALIGN {
+            true => adjust_request(size + align + gap_min).ok_or(AllocError)?,
+            false => size,
+        };
+
+        let block = self.take_block(search_size)?;
+
+        unsafe {
+            let control = self.control();
+            let mut block = block;
+
+            if align > ALIGN {
+                let ptr = payload(block) as usize;
+                let mut gap = round_up(ptr, align) - ptr;
+
+                if gap != 0 && gap < gap_min {
+                    let offset = core::cmp::max(gap_min - gap, align);
+                    gap = round_up(ptr + gap + offset, align) - ptr;
+                }
+
+                if gap != 0 {
+                    block = control.trim_free_leading(block, gap);
+                }
+            }
+
+            control.trim_free(block, size);
+            mark_used(block);
+
+            self.used.set(self.used.get() + block_size(block));
+
+            return Ok(payload(block));
+        }
+    }
+
+    unsafe fn resize(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+        zeroed: bool,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let (old_size, new_size) = (old_layout.size(), new_layout.size());
+        let block = from_payload(ptr.as_ptr());
+        let control = self.control();
+
+        let current = block_size(block);
+        let adjusted = adjust_request(new_size).ok_or(AllocError)?;
+        let aligned = (ptr.as_ptr() as usize).is_multiple_of(new_layout.align());
+
+        let mut in_place = aligned && adjusted <= current;
+        if aligned && adjusted > current {
+            let next = next_phys(block);
+
+            if is_free(next) && current + HEADER + block_size(next) >= adjusted {
+                control.merge_next(block);
+                mark_used(block);
+                in_place = true;
+            }
+        }
+
+        if in_place {
+            control.trim_used(block, adjusted);
+            self.used.set(self.used.get() - current + block_size(block));
+
+            if zeroed && new_size > old_size {
+                let tail = ptr.as_ptr().add(old_size);
+                tail.write_bytes(0, new_size - old_size);
+            }
+
+            return Ok(sized_slice(ptr.as_ptr(), new_size));
+        }
+
+        let new_ptr = match zeroed {
+            true => self.allocate_zeroed(new_layout)?,
+            false => self.allocate(new_layout)?,
+        };
+
+        let size = core::cmp::min(old_size, new_size);
+        ptr::copy_nonoverlapping(ptr.as_ptr(), new_ptr.as_ptr() as *mut u8, size);
+        self.deallocate(ptr, old_layout);
+
+        return Ok(new_ptr);
+    }
+}
+
+impl<'a> Drop for Tlsf<'a> {
+    fn drop(&mut self) {
+        let pools = self.pools.replace(Pod::new());
+
+        for pool in pools {
+            if pool.mapped {
+                unsafe {
+                    let _ = unmap_region(pool.ptr.as_ptr() as *const (), pool.size);
+                }
+            }
+        }
+    }
+}
+
+unsafe impl<'a> Allocator for Tlsf<'a> {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let ptr = self.allocate_impl(layout)?;
+
+        return Ok(sized_slice(ptr, layout.size()));
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        let control = self.control();
+        let block = from_payload(ptr.as_ptr());
+        self.used.set(self.used.get() - block_size(block));
+
+        mark_free(block);
+        let block = control.merge_prev(block);
+        let block = control.merge_next(block);
+        control.insert(block);
+    }
+
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return self.resize(ptr, old_layout, new_layout, false);
+    }
+
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return self.resize(ptr, old_layout, new_layout, true);
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        return self.resize(ptr, old_layout, new_layout, false);
+    }
+
+    fn owns(&self, ptr: NonNull<u8>) -> bool {
+        let pools = self.pools.replace(Pod::new());
+
+        let addr = ptr.as_ptr() as usize;
+        let owns = pools.iter().any(|pool| {
+            let begin = pool.ptr.as_ptr() as usize;
+
+            begin <= addr && addr < begin + pool.size
+        });
+
+        self.pools.replace(pools);
+
+        return owns;
+    }
+}
+
+impl<'a> AllocStat for Tlsf<'a> {
+    fn total_used(&self) -> usize {
+        return self.used.get();
+    }
+
+    fn total_capacity(&self) -> usize {
+        return self.capacity.get();
+    }
+}
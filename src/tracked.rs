@@ -0,0 +1,198 @@
+use crate::alloc_api::*;
+use alloc::alloc::Layout;
+use core::ptr::NonNull;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Number of buckets in the size-class histogram. Bucket `i` counts
+/// allocations with a size in `(2^(i-1), 2^i]`, and bucket 0 counts
+/// allocations of 0 or 1 bytes.
+pub const SIZE_CLASS_COUNT: usize = usize::BITS as usize + 1;
+
+#[inline]
+pub fn size_class(size: usize) -> usize {
+    if size <= 1 {
+        return 0;
+    }
+
+    return (usize::BITS - (size - 1).leading_zeros()) as usize;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TrackedStats {
+    pub live_bytes: usize,
+    pub peak_bytes: usize,
+    pub allocations: usize,
+    pub deallocations: usize,
+    pub grows: usize,
+    pub shrinks: usize,
+    pub size_classes: [usize; SIZE_CLASS_COUNT],
+}
+
+/// Forwards everything to `A` while counting what goes through it. The
+/// counters are atomic, so a `Tracked<A>` is `Sync` whenever `A` is.
+pub struct Tracked<A>
+where
+    A: Allocator,
+{
+    inner: A,
+    live_bytes: AtomicUsize,
+    peak_bytes: AtomicUsize,
+    allocations: AtomicUsize,
+    deallocations: AtomicUsize,
+    grows: AtomicUsize,
+    shrinks: AtomicUsize,
+    size_classes: [AtomicUsize; SIZE_CLASS_COUNT],
+}
+
+impl<A> Tracked<A>
+where
+    A: Allocator,
+{
+    pub const fn new(inner: A) -> Self {
+        return Self {
+            inner,
+            live_bytes: AtomicUsize::new(0),
+            peak_bytes: AtomicUsize::new(0),
+            allocations: AtomicUsize::new(0),
+            deallocations: AtomicUsize::new(0),
+            grows: AtomicUsize::new(0),
+            shrinks: AtomicUsize::new(0),
+            size_classes: [const { AtomicUsize::new(0) }; SIZE_CLASS_COUNT],
+        };
+    }
+
+    #[inline(always)]
+    pub fn inner(&self) -> &A {
+        return &self.inner;
+    }
+
+    pub fn into_inner(self) -> A {
+        return self.inner;
+    }
+
+    pub fn stats(&self) -> TrackedStats {
+        let mut size_classes = [0; SIZE_CLASS_COUNT];
+        for (count, class) in size_classes.iter_mut().zip(self.size_classes.iter()) {
+            *count = class.load(Ordering::Relaxed);
+        }
+
+        return TrackedStats {
+            live_bytes: self.live_bytes.load(Ordering::Relaxed),
+            peak_bytes: self.peak_bytes.load(Ordering::Relaxed),
+            allocations: self.allocations.load(Ordering::Relaxed),
+            deallocations: self.deallocations.load(Ordering::Relaxed),
+            grows: self.grows.load(Ordering::Relaxed),
+            shrinks: self.shrinks.load(Ordering::Relaxed),
+            size_classes,
+        };
+    }
+
+    /// Resets the peak to the number of bytes currently live.
+    pub fn reset_peak(&self) {
+        let live = self.live_bytes.load(Ordering::Relaxed);
+        self.peak_bytes.store(live, Ordering::Relaxed);
+    }
+
+    fn add_live(&self, bytes: usize) {
+        let live = self.live_bytes.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        self.peak_bytes.fetch_max(live, Ordering::Relaxed);
+    }
+
+    fn sub_live(&self, bytes: usize) {
+        self.live_bytes.fetch_sub(bytes, Ordering::Relaxed);
+    }
+
+    fn record_allocation(&self, layout: Layout) {
+        self.allocations.fetch_add(1, Ordering::Relaxed);
+        self.size_classes[size_class(layout.size())].fetch_add(1, Ordering::Relaxed);
+        self.add_live(layout.size());
+    }
+}
+
+unsafe impl<A> Allocator for Tracked<A>
+where
+    A: Allocator,
+{
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let ptr = self.inner.allocate(layout)?;
+        self.record_allocation(layout);
+
+        return Ok(ptr);
+    }
+
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let ptr = self.inner.allocate_zeroed(layout)?;
+        self.record_allocation(layout);
+
+        return Ok(ptr);
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.inner.deallocate(ptr, layout);
+
+        self.deallocations.fetch_add(1, Ordering::Relaxed);
+        self.sub_live(layout.size());
+    }
+
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let ptr = self.inner.grow(ptr, old_layout, new_layout)?;
+
+        self.grows.fetch_add(1, Ordering::Relaxed);
+        self.add_live(new_layout.size() - old_layout.size());
+
+        return Ok(ptr);
+    }
+
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let ptr = self.inner.grow_zeroed(ptr, old_layout, new_layout)?;
+
+        self.grows.fetch_add(1, Ordering::Relaxed);
+        self.add_live(new_layout.size() - old_layout.size());
+
+        return Ok(ptr);
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let ptr = self.inner.shrink(ptr, old_layout, new_layout)?;
+
+        self.shrinks.fetch_add(1, Ordering::Relaxed);
+        self.sub_live(old_layout.size() - new_layout.size());
+
+        return Ok(ptr);
+    }
+
+    #[inline]
+    fn owns(&self, ptr: NonNull<u8>) -> bool {
+        return self.inner.owns(ptr);
+    }
+}
+
+// `total_used` is the number of bytes currently live, and `total_capacity` is
+// the most that were ever live at once.
+impl<A> AllocStat for Tracked<A>
+where
+    A: Allocator,
+{
+    fn total_used(&self) -> usize {
+        return self.live_bytes.load(Ordering::Relaxed);
+    }
+
+    fn total_capacity(&self) -> usize {
+        return self.peak_bytes.load(Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,142 @@
+use crate::alloc_api::*;
+use crate::bump::*;
+use crate::pod::*;
+use core::cell::Cell;
+use core::ptr::NonNull;
+
+/// Arena for values of a single type that, unlike `BucketList`, runs their
+/// destructors when it's dropped. Values never move once they're allocated.
+pub struct TypedArena<T, A = Global>
+where
+    A: Allocator,
+{
+    buckets: BucketList<A>,
+
+    // Runs of values to drop, merged when they're next to each other
+    runs: Cell<Pod<(NonNull<T>, usize)>>,
+}
+
+unsafe impl<T, A> Send for TypedArena<T, A>
+where
+    T: Send,
+    A: Allocator + Send,
+{
+}
+
+impl<T> TypedArena<T> {
+    pub fn new() -> Self {
+        return Self::with_buckets(BucketList::new());
+    }
+}
+
+impl<T, A> TypedArena<T, A>
+where
+    A: Allocator,
+{
+    /// Buckets come from `allocator` instead of the global heap.
+    pub fn with_allocator(allocator: A) -> Self {
+        return Self::with_buckets(BucketList::with_allocator(allocator));
+    }
+
+    /// Uses `buckets` for storage, e.g. one made with a `BucketListBuilder`.
+    pub fn with_buckets(buckets: BucketList<A>) -> Self {
+        return Self {
+            buckets,
+            runs: Cell::new(Pod::new()),
+        };
+    }
+
+    // Handing out `&mut` from `&self` is the whole point of an arena
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc(&self, value: T) -> &mut T {
+        let value = self.buckets.new(value);
+        self.track(NonNull::from(&mut *value), 1);
+
+        return value;
+    }
+
+    /// The values end up next to each other, in iteration order. If the
+    /// iterator panics, the values it already produced are still dropped with
+    /// the arena.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_extend<I>(&self, iter: I) -> &mut [T]
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let iter = iter.into_iter();
+        let mut extend = Extend {
+            arena: self,
+            buf: GrowBuf::for_iter(&self.buckets, &iter),
+        };
+
+        for value in iter {
+            extend.buf.push(value);
+        }
+
+        extend.buf.shrink_to_fit();
+        let (ptr, len) = extend.buf.written();
+        drop(extend);
+
+        return unsafe { core::slice::from_raw_parts_mut(ptr.as_ptr(), len) };
+    }
+
+    /// Number of values allocated so far.
+    pub fn len(&self) -> usize {
+        let runs = self.runs.replace(Pod::new());
+        let len = runs.iter().map(|&(_, len)| len).sum();
+        self.runs.replace(runs);
+
+        return len;
+    }
+
+    fn track(&self, ptr: NonNull<T>, len: usize) {
+        let mut runs = self.runs.replace(Pod::new());
+
+        match runs.last_mut() {
+            Some((start, count)) if unsafe { start.as_ptr().add(*count) } == ptr.as_ptr() => {
+                *count += len;
+            }
+            _ => runs.push((ptr, len)),
+        }
+
+        self.runs.replace(runs);
+    }
+}
+
+impl<T, A> Drop for TypedArena<T, A>
+where
+    A: Allocator,
+{
+    fn drop(&mut self) {
+        let runs = self.runs.replace(Pod::new());
+
+        for &(ptr, len) in runs.iter() {
+            unsafe {
+                let values = core::ptr::slice_from_raw_parts_mut(ptr.as_ptr(), len);
+                core::ptr::drop_in_place(values);
+            }
+        }
+    }
+}
+
+// Registers the values written by `alloc_extend` when it's done, or when the
+// iterator panics partway through.
+struct Extend<'a, T, A>
+where
+    A: Allocator,
+{
+    arena: &'a TypedArena<T, A>,
+    buf: GrowBuf<'a, T, BucketList<A>>,
+}
+
+impl<T, A> Drop for Extend<'_, T, A>
+where
+    A: Allocator,
+{
+    fn drop(&mut self) {
+        let (ptr, len) = self.buf.written();
+        if len != 0 {
+            self.arena.track(ptr, len);
+        }
+    }
+}
@@ -0,0 +1,700 @@
+use aliu::*;
+use core::alloc::Layout;
+use core::ptr::NonNull;
+
+#[test]
+fn test_global_edge_cases() {
+    unsafe {
+        let zst = Layout::from_size_align(0, 64).unwrap();
+        let ptr = Global.allocate(zst).unwrap();
+        assert_eq!(ptr.as_ptr() as *mut u8 as usize % 64, 0);
+        Global.deallocate(ptr.cast(), zst);
+
+        let big = Layout::from_size_align(256, 4096).unwrap();
+        let ptr = Global.allocate_zeroed(big).unwrap();
+        assert_eq!(ptr.as_ptr() as *mut u8 as usize % 4096, 0);
+        assert!((*ptr.as_ptr()).iter().all(|&b| b == 0));
+        Global.deallocate(ptr.cast(), big);
+
+        let huge = Layout::from_size_align(isize::MAX as usize - 4096, 8).unwrap();
+        // Otherwise release builds elide the allocation and assume it worked
+        assert!(core::hint::black_box(Global.allocate(huge)).is_err());
+    }
+
+    let mut pod = Pod::<u8>::new();
+    for i in 0..100_000u32 {
+        pod.push(i as u8);
+    }
+
+    assert!(pod.iter().enumerate().all(|(i, &b)| b == i as u8));
+
+    pod.truncate(10);
+    pod.shrink_to_fit();
+    assert_eq!(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9], &pod[..]);
+}
+
+#[test]
+fn test_tracked() {
+    let tracked = Tracked::new(Global);
+
+    {
+        let mut pod = Pod::<u64, _>::with_allocator(&tracked);
+        pod.reserve(4);
+        pod.push_repeat(1, 16);
+
+        assert_eq!(tracked.total_used(), pod.capacity() * 8);
+    }
+
+    let stats = tracked.stats();
+    assert_eq!(stats.live_bytes, 0);
+    assert_eq!(stats.peak_bytes, 16 * 8);
+    assert_eq!(stats.allocations, 1);
+    assert_eq!(stats.deallocations, 1);
+    assert_eq!(stats.grows, 1);
+    assert_eq!(stats.size_classes[size_class(32)], 1);
+    assert_eq!(stats.size_classes.iter().sum::<usize>(), 1);
+}
+
+#[test]
+fn test_checked() {
+    let checked = CheckedAllocator::new(Global);
+
+    {
+        let mut pod = Pod::<u32, _>::with_allocator(&checked);
+        pod.push_repeat(3, 100);
+        pod.truncate(5);
+        pod.shrink_to_fit();
+
+        let map = HashRef::new_iter(&checked, 8, (0..4u32).map(|i| (i, i * 2)));
+        assert_eq!(map.get(&3), Some(&6));
+
+        assert_eq!(checked.live_count(), 2);
+    }
+
+    let leaks = checked.leaks();
+    let slot_size = core::mem::size_of::<HashRefSlot<u32, u32>>();
+    assert_eq!(leaks.len(), 1);
+    assert_eq!(leaks[0usize].1.size(), 8 * slot_size);
+    checked.forget_all();
+}
+
+#[test]
+#[should_panic(expected = "double free")]
+fn test_checked_double_free() {
+    let checked = CheckedAllocator::new(Global);
+    let layout = Layout::new::<u64>();
+
+    unsafe {
+        let ptr = checked.allocate(layout).unwrap().cast();
+        checked.deallocate(ptr, layout);
+        checked.deallocate(ptr, layout);
+    }
+}
+
+#[test]
+#[should_panic(expected = "never allocated")]
+fn test_checked_forgets_old_frees() {
+    let checked = CheckedAllocator::new(Global);
+    let layout = Layout::new::<u64>();
+
+    unsafe {
+        let mut ptrs = Pod::new();
+        for _ in 0..2000 {
+            ptrs.push(checked.allocate(layout).unwrap().cast::<u8>());
+        }
+
+        for &ptr in ptrs.iter() {
+            checked.deallocate(ptr, layout);
+        }
+
+        // Long enough ago that it's no longer remembered as freed
+        checked.deallocate(ptrs[0usize], layout);
+    }
+}
+
+#[test]
+#[should_panic(expected = "layout mismatch")]
+fn test_checked_layout_mismatch() {
+    let checked = CheckedAllocator::new(Global);
+
+    unsafe {
+        let ptr = checked.allocate(Layout::new::<u64>()).unwrap().cast();
+        checked.deallocate(ptr, Layout::new::<u32>());
+    }
+}
+
+#[test]
+#[should_panic(expected = "1 block(s) leaked")]
+fn test_checked_leak() {
+    let checked = CheckedAllocator::new(Global);
+    checked.new(12u64);
+}
+
+#[test]
+fn test_failing_pod() {
+    let failing = FailingAllocator::fail_nth(Global, 2);
+    let mut pod = Pod::<u64, _>::with_allocator(&failing);
+
+    pod.try_push(1).unwrap();
+    pod.try_push(2).unwrap();
+    assert_eq!(pod.try_reserve(10), Err(PodError::AllocFailure));
+    assert_eq!(&[1, 2], &pod[..]);
+    assert_eq!(pod.capacity(), 2);
+
+    pod.try_reserve(10).unwrap();
+    assert_eq!(failing.failures(), 1);
+
+    let budget = FailingAllocator::with_budget(Global, 64);
+    let mut pod = Pod::<u64, _>::with_allocator(&budget);
+    pod.try_push_repeat(0, 8).unwrap();
+    assert_eq!(pod.try_push(1), Err(PodError::AllocFailure));
+    assert_eq!(pod.len(), 8);
+}
+
+#[test]
+fn test_failing_replay() {
+    let run = |failing: &FailingAllocator<Global>| {
+        let mut failed_at = Pod::new();
+        for i in 0..100 {
+            let mut pod = Pod::<u8, _>::with_allocator(failing);
+            if pod.try_push(i).is_err() {
+                failed_at.push(i);
+            }
+        }
+
+        failed_at
+    };
+
+    let failing = FailingAllocator::random(Global, 1234, 4);
+    let first = run(&failing);
+    assert!(!first.is_empty() && first.len() < 100);
+
+    failing.reset();
+    assert_eq!(first, run(&failing));
+    assert_eq!(first, run(&FailingAllocator::random(Global, 1234, 4)));
+}
+
+#[test]
+fn test_arena_box() {
+    use std::rc::Rc;
+
+    let counter = Rc::new(());
+    let checked = CheckedAllocator::new(Global);
+
+    {
+        let boxed = checked.new_box(Pod::<u32>::new());
+        let mut cloned = checked.new_box(counter.clone());
+        assert_eq!(Rc::strong_count(&counter), 2);
+        assert_eq!(boxed.len(), 0);
+
+        *cloned = counter.clone();
+        assert_eq!(Rc::strong_count(&counter), 2);
+
+        let value = ArenaBox::into_inner(checked.new_box(counter.clone()));
+        assert_eq!(checked.live_count(), 2);
+        drop(value);
+    }
+
+    assert_eq!(Rc::strong_count(&counter), 1);
+    assert_eq!(checked.live_count(), 0);
+
+    let bucket_list = BucketList::new();
+    let s = bucket_list.add_str("hello");
+    let n = bucket_list.new(1u64);
+    *n += 1;
+    assert_eq!(s, "hello");
+    assert_eq!(*n, 2);
+}
+
+#[test]
+fn test_alloc_ext_builders() {
+    let bucket_list = BucketList::new();
+
+    let exact = bucket_list.add_iter((0..5u32).map(|i| i * i));
+    assert_eq!(exact, &[0, 1, 4, 9, 16]);
+
+    let filtered = Global.add_iter((0..100u32).filter(|i| i % 7 == 0));
+    assert_eq!(filtered.len(), 15);
+    assert_eq!(filtered[14], 98);
+
+    let mut count = 0;
+    let units = bucket_list.add_iter(core::iter::from_fn(|| {
+        count += 1;
+        (count <= 3).then_some(())
+    }));
+    assert_eq!(units.len(), 3);
+
+    let s = alloc_format!(&bucket_list, "{}-{:?}", 12, "hi");
+    assert_eq!(s, "12-\"hi\"");
+    let long = alloc_format!(Global, "{:0>200}", 1);
+    assert_eq!(long.len(), 200);
+
+    let c = bucket_list.add_cstr("hello");
+    assert_eq!(c.to_bytes_with_nul(), b"hello\0");
+
+    let zeroes = Global.add_fill(16, 0u8);
+    assert_eq!(zeroes, &[0; 16]);
+    let strings = bucket_list.add_fill(2, "a");
+    assert_eq!(strings, &["a", "a"]);
+
+    let uninit = bucket_list.add_uninit::<u64>(4);
+    assert_eq!(uninit.len(), 4);
+    assert_eq!(uninit.as_ptr() as usize % 8, 0);
+}
+
+#[test]
+fn test_segregator() {
+    let bucket_list = BucketList::new();
+    let large = CheckedAllocator::new(Global);
+    let segregator = Segregator::<64, _, _>::new(&bucket_list, &large);
+
+    let mut pod = Pod::<u64, _>::with_allocator(&segregator);
+    pod.push_repeat(1, 8);
+    assert_eq!(large.live_count(), 0);
+    assert!(segregator.owns(NonNull::from(&pod[0usize]).cast()));
+
+    pod.push(2);
+    assert_eq!(large.live_count(), 1);
+    assert_eq!(pod.len(), 9);
+    assert!(pod[..8].iter().all(|&x| x == 1));
+
+    pod.truncate(2);
+    pod.shrink_to_fit();
+    assert_eq!(large.live_count(), 0);
+    assert_eq!(&[1, 1], &pod[..]);
+}
+
+#[test]
+fn test_fallback() {
+    let bucket_list = BucketList::new();
+    let primary = FailingAllocator::fail_nth(&bucket_list, 1);
+    let secondary = CheckedAllocator::new(Global);
+    let fallback = Fallback::new(&primary, &secondary);
+
+    let a = fallback.new(1u64);
+    let b = fallback.new_box(2u64);
+    let c = fallback.new(3u64);
+
+    assert!(bucket_list.owns(NonNull::from(&*a).cast()));
+    assert!(!bucket_list.owns(NonNull::from(&*b).cast()));
+    assert!(bucket_list.owns(NonNull::from(&*c).cast()));
+    assert_eq!(secondary.live_count(), 1);
+
+    drop(b);
+    assert_eq!(secondary.live_count(), 0);
+    assert_eq!(*a + *c, 4);
+}
+
+#[test]
+fn test_pool() {
+    let pool = Pool::for_type::<[u64; 3]>();
+    let block_size = pool.block_size();
+
+    let a = pool.new_box([1u64, 2, 3]);
+    let a_addr = a.as_ptr() as usize;
+    drop(a);
+
+    let b = pool.new([4u64, 5, 6]);
+    assert_eq!(b.as_ptr() as usize, a_addr);
+    assert_eq!(pool.total_used(), block_size);
+
+    let blocks: Pod<_> = (0..10_000)
+        .map(|i| pool.new([i as u64; 3]) as *mut [u64; 3])
+        .collect();
+    assert!(pool.total_capacity() >= 10_000 * block_size);
+    assert!(blocks
+        .iter()
+        .all(|&b| pool.owns(NonNull::new(b).unwrap().cast())));
+
+    let too_big = Layout::from_size_align(block_size + 1, 8).unwrap();
+    assert!(pool.allocate(too_big).is_err());
+
+    let bytes = Pool::new(Layout::from_size_align(256, 8).unwrap());
+    let mut pod = Pod::<u8, _>::with_allocator(&bytes);
+    pod.push(1);
+    let first = pod.as_ptr();
+    pod.push_repeat(2, 255);
+    assert_eq!(first, pod.as_ptr());
+    assert_eq!(pod.try_push(3), Err(PodError::AllocFailure));
+}
+
+#[test]
+fn test_slab() {
+    let slab = Slab::new();
+
+    let a = slab.new(1u64);
+    let a_addr = a as *mut u64 as usize;
+    unsafe { slab.deallocate(NonNull::from(&*a).cast(), Layout::new::<u64>()) };
+    assert_eq!(slab.new(2u64) as *mut u64 as usize, a_addr);
+
+    let aligned = Layout::from_size_align(100, 256).unwrap();
+    let ptr = slab.allocate(aligned).unwrap();
+    assert_eq!(ptr.as_ptr() as *mut u8 as usize % 256, 0);
+    assert_eq!(slab.total_used(), 8 + 100);
+
+    let mut pod = Pod::<u8, _>::with_allocator(&slab);
+    pod.push_repeat(7, 10_000);
+    assert!(slab.owns(NonNull::new(pod.as_ptr() as *mut u8).unwrap()));
+    let large_capacity = slab.total_capacity();
+
+    pod.truncate(5_000);
+    pod.shrink_to_fit();
+    let first = pod.as_ptr();
+    pod.push_repeat(8, 100);
+    assert_eq!(first, pod.as_ptr());
+    assert!(pod[..5_000].iter().all(|&b| b == 7));
+
+    pod.truncate(10);
+    pod.shrink_to_fit();
+    assert!(slab.total_capacity() < large_capacity);
+    assert_eq!(&[7; 10], &pod[..]);
+    assert!(slab.total_used() <= slab.total_capacity());
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_sync_bucket_list() {
+    let arena = SyncBucketList::new();
+    let values: Vec<Vec<&u64>> = std::thread::scope(|s| {
+        let handles: Vec<_> = (0..4u64)
+            .map(|t| {
+                let arena = &arena;
+                s.spawn(move || {
+                    (0..100_000u64)
+                        .map(|i| &*arena.new(t * 1_000_000 + i))
+                        .collect()
+                })
+            })
+            .collect();
+
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+
+    for (t, values) in values.iter().enumerate() {
+        let expected = (0..100_000u64).map(|i| t as u64 * 1_000_000 + i);
+        assert!(values.iter().map(|&&v| v).eq(expected));
+    }
+
+    assert_eq!(arena.total_used(), 4 * 100_000 * 8);
+    assert!(arena.total_capacity() >= arena.total_used());
+    assert!(arena.owns(NonNull::from(values[3][99_999]).cast()));
+
+    let aligned = arena.allocate(Layout::from_size_align(64, 64).unwrap());
+    assert_eq!(aligned.unwrap().as_ptr() as *mut u8 as usize % 64, 0);
+}
+
+#[test]
+fn test_stack_allocator() {
+    let mut stack = StackAllocator::with_capacity(1024 * 1024);
+
+    {
+        let mut pod = Pod::<u32, _>::with_allocator(&stack);
+        pod.push(0);
+        let first = pod.as_ptr();
+        for i in 1..100_000 {
+            pod.push(i);
+        }
+
+        assert_eq!(first, pod.as_ptr());
+        assert_eq!(stack.total_used(), pod.capacity() * 4);
+
+        pod.truncate(10);
+        pod.shrink_to_fit();
+        assert_eq!(stack.total_used(), 40);
+    }
+
+    assert_eq!(stack.total_used(), 0);
+
+    let a = stack.new(1u64) as *mut u64;
+    let mark = stack.save();
+    {
+        let scoped = stack.scoped();
+        scoped.add_slice(&[2u8; 1000]);
+        assert_eq!(scoped.total_used(), 1000);
+    }
+
+    assert_eq!(stack.total_used(), 8);
+    unsafe {
+        stack.new(2u64);
+        stack.set(mark);
+        assert_eq!(*a, 1);
+    }
+
+    assert_eq!(stack.total_used(), 8);
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "out-of-order free")]
+fn test_stack_out_of_order() {
+    let stack = StackAllocator::with_capacity(4096);
+    let a = stack.new_box(1u64);
+    let b = stack.new_box(2u64);
+    drop(a);
+    drop(b);
+}
+
+#[test]
+fn test_buddy() {
+    let buddy = BuddyAllocator::with_capacity(64 * 1024);
+    let whole = format!("{:?}", buddy);
+
+    let blocks: Pod<_> = (0..100u64).map(|i| buddy.new(i) as *mut u64).collect();
+    assert_eq!(buddy.total_used(), 100 * BuddyAllocator::MIN_BLOCK);
+    assert!(blocks
+        .iter()
+        .enumerate()
+        .all(|(i, &b)| unsafe { *b } == i as u64));
+
+    for &b in blocks.iter().rev() {
+        unsafe { buddy.deallocate(NonNull::new(b).unwrap().cast(), Layout::new::<u64>()) };
+    }
+
+    assert_eq!(buddy.total_used(), 0);
+    assert_eq!(format!("{:?}", buddy), whole);
+
+    let mut pod = Pod::<u8, _>::with_allocator(&buddy);
+    pod.push(1);
+    let first = pod.as_ptr();
+    pod.push_repeat(2, 1000);
+    assert_eq!(first, pod.as_ptr());
+
+    let map = HashRef::new_iter(&buddy, 16, (0..10u32).map(|i| (i, i + 1)));
+    assert_eq!(map.get(&9), Some(&10));
+
+    let huge = Layout::from_size_align(128 * 1024, 8).unwrap();
+    assert!(buddy.allocate(huge).is_err());
+}
+
+#[test]
+fn test_tlsf() {
+    let mut buffer = [core::mem::MaybeUninit::<u8>::uninit(); 64 * 1024];
+    let tlsf = Tlsf::from_buffer(&mut buffer);
+    let capacity = tlsf.total_capacity();
+
+    let mut state = 7u64;
+    let mut live: Vec<(NonNull<u8>, Layout, u8)> = Vec::new();
+    for i in 0..10_000 {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+
+        if state.is_multiple_of(3) && !live.is_empty() {
+            let (ptr, layout, tag) = live.swap_remove(state as usize % live.len());
+            let bytes = unsafe { core::slice::from_raw_parts(ptr.as_ptr(), layout.size()) };
+            assert!(bytes.iter().all(|&b| b == tag));
+            unsafe { tlsf.deallocate(ptr, layout) };
+            continue;
+        }
+
+        let size = (state >> 8) as usize % 700 + 1;
+        let align = 1 << ((state >> 20) % 8);
+        let layout = Layout::from_size_align(size, align).unwrap();
+        if let Ok(ptr) = tlsf.allocate(layout) {
+            let ptr = ptr.cast::<u8>();
+            assert_eq!(ptr.as_ptr() as usize % align, 0);
+            unsafe { ptr.as_ptr().write_bytes(i as u8, size) };
+            live.push((ptr, layout, i as u8));
+        }
+    }
+
+    for (ptr, layout, _) in live {
+        unsafe { tlsf.deallocate(ptr, layout) };
+    }
+
+    // Everything merged back together
+    assert_eq!(tlsf.total_used(), 0);
+    let nearly_all = Layout::from_size_align(capacity / 16 * 15, 8).unwrap();
+    let all = tlsf.allocate(nearly_all).unwrap();
+    unsafe { tlsf.deallocate(all.cast(), nearly_all) };
+
+    let mut pod = Pod::<u64, _>::with_allocator(&tlsf);
+    pod.push(1);
+    let first = pod.as_ptr();
+    pod.push_repeat(2, 1000);
+    assert_eq!(first, pod.as_ptr());
+    assert!(pod.try_push_repeat(3, 64 * 1024).is_err());
+
+    let mapped = Tlsf::new();
+    let big = mapped.add_slice(&[5u8; 3 * 1024 * 1024]);
+    assert!(big.iter().all(|&b| b == 5));
+    assert!(mapped.owns(NonNull::from(&big[0]).cast()));
+}
+
+#[test]
+fn test_fixed_buffer() {
+    let mut buffer = [core::mem::MaybeUninit::uninit(); 256];
+    let fixed = FixedBufferAllocator::new(&mut buffer);
+
+    let mut pod = Pod::<u32, _>::with_allocator(&fixed);
+    pod.try_push(1).unwrap();
+    let first = pod.as_ptr();
+    pod.try_push_repeat(2, 63).unwrap();
+    assert_eq!(first, pod.as_ptr());
+    assert_eq!(pod.try_push(3), Err(PodError::AllocFailure));
+    assert_eq!(pod.len(), 64);
+    drop(pod);
+    assert_eq!(fixed.total_used(), 0);
+
+    let storage = InlineArena::<1024>::new();
+    let arena = storage.allocator();
+    let map = HashRef::new_iter(arena, 8, (0..4u32).map(|i| (i, i * 3)));
+    assert_eq!(map.get(&2), Some(&6));
+    assert!(arena.owns(NonNull::from(map.get(&2).unwrap()).cast()));
+
+    let too_big = Layout::from_size_align(2048, 8).unwrap();
+    assert!(arena.allocate(too_big).is_err());
+}
+
+#[test]
+fn test_typed_arena() {
+    use std::rc::Rc;
+
+    let counter = Rc::new(());
+
+    {
+        let arena = TypedArena::new();
+        let first = arena.alloc((String::from("first"), counter.clone()));
+        let address = first as *const _;
+
+        let rest = arena.alloc_extend((0..100).map(|i| (i.to_string(), counter.clone())));
+        assert_eq!(rest.len(), 100);
+        assert_eq!(rest[42].0, "42");
+
+        for _ in 0..1000 {
+            arena.alloc((String::new(), counter.clone()));
+        }
+
+        assert_eq!(first.0, "first");
+        assert_eq!(first as *const _, address);
+        assert_eq!(arena.len(), 1101);
+        assert_eq!(Rc::strong_count(&counter), 1102);
+
+        let empty = arena.alloc_extend(std::iter::empty());
+        assert_eq!(empty.len(), 0);
+    }
+
+    assert_eq!(Rc::strong_count(&counter), 1);
+
+    struct Counted<'a>(&'a std::cell::Cell<usize>);
+
+    impl Drop for Counted<'_> {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    let drops = std::cell::Cell::new(0);
+
+    {
+        let arena = TypedArena::new();
+        arena.alloc(Counted(&drops));
+        arena.alloc_extend((0..10).map(|_| Counted(&drops)));
+        arena.alloc(Counted(&drops));
+        assert_eq!(drops.get(), 0);
+    }
+
+    assert_eq!(drops.get(), 12);
+
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        let arena = TypedArena::new();
+        arena.alloc(Counted(&drops));
+        arena.alloc_extend((0..100).map(|i| {
+            assert!(i < 50, "iterator panicked");
+            Counted(&drops)
+        }));
+    }));
+
+    assert!(result.is_err());
+    assert_eq!(drops.get(), 12 + 51);
+
+    let tracked = Tracked::new(Global);
+
+    {
+        let arena = TypedArena::with_allocator(&tracked);
+        arena.alloc_extend((0..10).map(|_| Counted(&drops)));
+        assert!(tracked.total_used() >= 10 * core::mem::size_of::<Counted>());
+    }
+
+    assert_eq!(drops.get(), 12 + 51 + 10);
+    assert_eq!(tracked.total_used(), 0);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_arena_herd() {
+    let herd = ArenaHerd::new();
+
+    let slices: Vec<&[u64]> = std::thread::scope(|s| {
+        let workers: Vec<_> = (0..4u64)
+            .map(|i| {
+                let herd = &herd;
+                s.spawn(move || {
+                    // Through the herd, the data lives as long as the herd
+                    let data = herd.add_iter((0..1000).map(|x| x * i));
+
+                    let member = herd.member();
+                    let more = member.add_slice(&[i; 24]);
+                    assert_eq!(member.total_used(), 1024 * 8);
+                    assert_eq!(more.as_ptr(), data.as_ptr().wrapping_add(1000));
+
+                    &*data
+                })
+            })
+            .collect();
+
+        workers.into_iter().map(|w| w.join().unwrap()).collect()
+    });
+
+    assert_eq!(herd.member_count(), 4);
+    for (i, data) in slices.iter().enumerate() {
+        assert_eq!(data[999], 999 * i as u64);
+        assert!(herd.owns(NonNull::from(&data[0]).cast()));
+    }
+
+    assert_eq!(herd.total_used(), 4 * 1024 * 8);
+    assert_eq!(herd.total_capacity(), 4 * BucketList::DEFAULT_BUCKET_SIZE);
+
+    herd.add_slice(&[1u8; 8]);
+    assert_eq!(herd.member_count(), 5);
+    assert_eq!(herd.member().total_used(), 8);
+
+    // Switching between herds, or making a new one where an old one was,
+    // must not mix up their members
+    let other = ArenaHerd::new();
+    other.add_slice(&[2u8; 16]);
+    assert_eq!(other.member().total_used(), 16);
+    assert_eq!(herd.member().total_used(), 8);
+
+    drop(other);
+    let third = ArenaHerd::new();
+    assert_eq!(third.member().total_used(), 0);
+    assert_eq!(third.member_count(), 1);
+}
+
+#[test]
+fn test_slab_zeroed() {
+    let slab = Slab::new();
+    let large = Layout::from_size_align(64 * 1024, 4096).unwrap();
+
+    unsafe {
+        // Without an OS, large blocks come from `Global`, which is likely to
+        // hand this memory right back
+        let dirty = Global.allocate(large).unwrap().cast::<u8>();
+        dirty.as_ptr().write_bytes(0xAB, large.size());
+        Global.deallocate(dirty, large);
+
+        let zeroed = slab.allocate_zeroed(large).unwrap();
+        assert!(zeroed.as_ref().iter().all(|&b| b == 0));
+        slab.deallocate(zeroed.cast(), large);
+
+        let small = Layout::from_size_align(64, 8).unwrap();
+        let ptr = slab.allocate(small).unwrap().cast::<u8>();
+        ptr.as_ptr().write_bytes(0xAB, small.size());
+        slab.deallocate(ptr, small);
+
+        let zeroed = slab.allocate_zeroed(small).unwrap();
+        assert!(zeroed.as_ref().iter().all(|&b| b == 0));
+        slab.deallocate(zeroed.cast(), small);
+    }
+}
@@ -46,3 +46,255 @@ fn test_basics() {
     assert_eq!(&1, &data[c]);
     assert_eq!(&1, &data[d]);
 }
+
+#[test]
+fn test_pod_empty_alignment() {
+    let mut pod = Pod::<u64>::new();
+    assert_eq!(pod.as_ptr() as usize % 8, 0);
+    assert_eq!(&pod[..], &[] as &[u64]);
+
+    pod.push(1);
+    pod.clear();
+    pod.shrink_to_fit();
+    assert_eq!(pod.as_ptr() as usize % 8, 0);
+}
+
+#[test]
+fn test_pod_try_reserve() {
+    let mut pod = pod![1u64, 2, 3];
+
+    assert_eq!(pod.try_reserve(usize::MAX), Err(PodError::LayoutFailure));
+    assert_eq!(
+        pod.try_resize(usize::MAX / 2, 0),
+        Err(PodError::LayoutFailure)
+    );
+    assert_eq!(&[1, 2, 3], &pod[..]);
+
+    pod.try_push(4).unwrap();
+    pod.try_extend_from_slice(&[5, 6]).unwrap();
+    pod.try_insert(0, 0).unwrap();
+    pod.try_splice(1..3, &[9]).unwrap();
+    pod.try_push_repeat(7, 2).unwrap();
+
+    assert_eq!(&[0, 9, 3, 4, 5, 6, 7, 7], &pod[..]);
+}
+
+#[test]
+fn test_bucket_list_alignment() {
+    let mut bucket_list = BucketList::with_capacity(128);
+
+    bucket_list.new(1u8);
+    let used = bucket_list.total_used();
+
+    for align in [16, 64, 4096] {
+        let layout = core::alloc::Layout::from_size_align(24, align).unwrap();
+        let ptr = bucket_list.allocate(layout).unwrap();
+
+        assert_eq!(ptr.as_ptr() as *mut u8 as usize % align, 0);
+    }
+
+    // A 4096-aligned block doesn't fit in the first bucket, so the fresh one
+    // has to make room for the padding
+    let layout =
+        core::alloc::Layout::from_size_align(BucketList::DEFAULT_BUCKET_SIZE, 4096).unwrap();
+    let ptr = bucket_list.allocate(layout).unwrap();
+    assert_eq!(ptr.as_ptr() as *mut u8 as usize % 4096, 0);
+    assert!(bucket_list.total_used() > used + 3 * 24 + BucketList::DEFAULT_BUCKET_SIZE);
+
+    {
+        let scoped = bucket_list.scoped();
+        let layout = core::alloc::Layout::from_size_align(8, 64).unwrap();
+        scoped.allocate(layout).unwrap();
+
+        assert!(scoped.total_used() >= 8);
+        assert!(scoped.total_used() < 64 + 8);
+    }
+}
+
+#[test]
+fn test_bucket_list_resize_in_place() {
+    let mut bucket_list = BucketList::new();
+
+    {
+        let mut pod = Pod::<u64, _>::with_allocator(&bucket_list);
+        for i in 0..1000 {
+            pod.push(i);
+        }
+
+        assert_eq!(bucket_list.total_used(), pod.capacity() * 8);
+    }
+
+    let used = bucket_list.total_used();
+    let layout = core::alloc::Layout::from_size_align(64, 8).unwrap();
+    let grown = core::alloc::Layout::from_size_align(128, 8).unwrap();
+
+    unsafe {
+        let ptr = bucket_list.allocate(layout).unwrap().cast::<u8>();
+        let same = bucket_list.grow(ptr, layout, grown).unwrap();
+        assert_eq!(same.cast::<u8>(), ptr);
+        assert_eq!(bucket_list.total_used(), used + 128);
+
+        let same = bucket_list.shrink(ptr, grown, layout).unwrap();
+        assert_eq!(same.cast::<u8>(), ptr);
+        assert_eq!(bucket_list.total_used(), used + 64);
+
+        let scoped = bucket_list.scoped();
+
+        // The block is from before the scope, so it has to move
+        let moved = scoped.grow(ptr, layout, grown).unwrap();
+        assert_ne!(moved.cast::<u8>(), ptr);
+        assert_eq!(scoped.total_used(), 128);
+
+        let moved = moved.cast::<u8>();
+        moved.as_ptr().write_bytes(1, 128);
+
+        let larger = core::alloc::Layout::from_size_align(256, 8).unwrap();
+        let same = scoped.grow_zeroed(moved, grown, larger).unwrap();
+        assert_eq!(same.cast::<u8>(), moved);
+        assert_eq!(
+            &same.as_ref()[120..136],
+            &[1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(scoped.total_used(), 256);
+    }
+}
+
+#[test]
+fn test_bucket_list_reuse() {
+    let mut bucket_list = BucketList::with_capacity(1024);
+    bucket_list.add_slice(&[1u8; 512]);
+
+    let mark = bucket_list.save();
+    for _ in 0..3 {
+        let data = bucket_list.add_slice(&[2u8; 512]);
+        bucket_list.add_slice(&[3u8; 4096]);
+
+        assert_eq!(data, &[2u8; 512]);
+        unsafe { bucket_list.set(mark) };
+    }
+
+    // Every frame after the first reuses the bucket from the first one
+    assert_eq!(bucket_list.total_used(), 512);
+    assert_eq!(
+        bucket_list.total_capacity(),
+        1024 + BucketList::DEFAULT_BUCKET_SIZE
+    );
+
+    {
+        let scoped = bucket_list.scoped();
+        scoped.add_slice(&[4u8; 256]);
+    }
+
+    // Rewinding to a mark keeps what was allocated before it
+    let data = bucket_list.add_slice(&[5u8; 256]);
+    assert_eq!(data, &[5u8; 256]);
+    assert_eq!(bucket_list.total_used(), 768);
+
+    bucket_list.release_unused(0);
+    assert_eq!(bucket_list.total_capacity(), 1024);
+
+    bucket_list.add_slice(&[6u8; 4096]);
+    bucket_list.reset();
+    assert_eq!(bucket_list.total_used(), 0);
+    assert_eq!(
+        bucket_list.total_capacity(),
+        1024 + BucketList::DEFAULT_BUCKET_SIZE
+    );
+
+    bucket_list.release_unused(BucketList::DEFAULT_BUCKET_SIZE);
+    assert_eq!(
+        bucket_list.total_capacity(),
+        1024 + BucketList::DEFAULT_BUCKET_SIZE
+    );
+}
+
+#[test]
+fn test_bucket_list_builder() {
+    let bucket_list = BucketList::builder()
+        .initial_size(1024)
+        .growth_factor(2)
+        .max_bucket_size(4096)
+        .memory_limit(15 * 1024)
+        .build();
+
+    for _ in 0..4 {
+        bucket_list.add_slice(&[1u8; 1000]);
+    }
+
+    // 1024, 2048 and 4096 bytes, then capped at 4096
+    assert_eq!(bucket_list.total_capacity(), 1024 + 2048 + 4096);
+
+    bucket_list.add_slice(&[1u8; 5000]);
+    assert_eq!(bucket_list.total_capacity(), 1024 + 2048 + 4096 + 5000);
+
+    // The last bucket gets clamped to what's left under the limit
+    bucket_list.add_slice(&[1u8; 2000]);
+    assert_eq!(bucket_list.total_capacity(), 15 * 1024);
+
+    let layout = core::alloc::Layout::from_size_align(4096, 8).unwrap();
+    assert!(bucket_list.allocate(layout).is_err());
+    assert_eq!(bucket_list.total_capacity(), 15 * 1024);
+
+    // A growth factor of 0 behaves like 1, so a zero-size allocation that
+    // doesn't fit at the end of a full bucket still gets a new one
+    let flat = BucketList::builder()
+        .initial_size(60)
+        .growth_factor(0)
+        .build();
+    flat.add_slice(&[1u8; 60]);
+    flat.add_slice(&[1u8; 60]);
+    assert_eq!(flat.total_capacity(), 120);
+    assert_eq!(flat.add_slice::<u64>(&[]), &[]);
+    assert_eq!(flat.total_capacity(), 180);
+
+    let capped = BucketList::builder()
+        .initial_size(1 << 20)
+        .max_bucket_size(4096)
+        .build();
+    capped.add_slice(&[1u8; 8]);
+    assert_eq!(capped.total_capacity(), 4096);
+
+    let mut zeroed = BucketList::builder().initial_size(256).zeroed(true).build();
+    zeroed.add_slice(&[0xffu8; 256]);
+    zeroed.reset();
+
+    let layout = core::alloc::Layout::from_size_align(256, 8).unwrap();
+    let data = zeroed.allocate(layout).unwrap();
+    assert_eq!(unsafe { data.as_ref() }, &[0u8; 256]);
+}
+
+#[test]
+fn test_bucket_list_with_allocator() {
+    let tracked = Tracked::new(Global);
+
+    {
+        let bucket_list = BucketListBuilder::new()
+            .initial_size(4096)
+            .build_with_allocator(&tracked);
+
+        bucket_list.add_slice(&[1u64; 100]);
+        bucket_list.add_slice(&[2u64; 1000]);
+
+        let stats = tracked.stats();
+        assert_eq!(stats.allocations, 2);
+        assert_eq!(stats.live_bytes, bucket_list.total_capacity());
+    }
+
+    assert_eq!(tracked.stats().live_bytes, 0);
+
+    let mut buffer = [core::mem::MaybeUninit::uninit(); 8192];
+    let fixed = FixedBufferAllocator::new(&mut buffer);
+
+    let mut inner = BucketList::with_allocator(&fixed);
+    {
+        let scoped = inner.scoped();
+        let data = scoped.add_slice(&[3u8; 6000]);
+        assert_eq!(data, &[3u8; 6000]);
+        assert!(fixed.owns(core::ptr::NonNull::from(&data[0])));
+    }
+
+    // The buffer can't fit a second bucket
+    let layout = core::alloc::Layout::from_size_align(4096, 8).unwrap();
+    assert!(inner.allocate(layout).is_ok());
+    assert!(inner.allocate(layout).is_err());
+}
@@ -0,0 +1,28 @@
+#![cfg(feature = "std")]
+
+use aliu::*;
+
+aliu::global_allocator!(HEAP: Tracked<System> = Tracked::new(System));
+
+#[test]
+fn test_global_adapter() {
+    let before = HEAP.stats().allocations;
+
+    let mut data = Vec::with_capacity(10);
+    data.extend_from_slice(&[1u64; 10]);
+    data.extend_from_slice(&[2u64; 1000]);
+
+    let stats = HEAP.stats();
+    assert!(stats.allocations > before);
+    assert!(stats.grows >= 1);
+    assert!(HEAP.total_used() >= 1010 * 8);
+
+    let mut pod = Pod::<u64>::new();
+    pod.push_repeat(3, 4096);
+    assert!(HEAP.total_used() >= (1010 + 4096) * 8);
+
+    drop(data);
+    drop(pod);
+
+    assert!(HEAP.total_capacity() >= (1010 + 4096) * 8);
+}
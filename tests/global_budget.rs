@@ -0,0 +1,29 @@
+#![cfg(feature = "std")]
+
+use aliu::*;
+
+aliu::global_allocator!(HEAP: Budgeted<System> = Budgeted::new(System, 64 << 20));
+
+#[test]
+fn test_global_budget() {
+    let mut data = Vec::<u8>::new();
+    data.try_reserve_exact(1 << 20).unwrap();
+    assert!(HEAP.live_bytes() >= 1 << 20);
+
+    let mut too_big = Vec::<u8>::new();
+    assert!(too_big.try_reserve_exact(64 << 20).is_err());
+
+    let live = HEAP.live_bytes();
+    drop(data);
+    assert!(HEAP.live_bytes() < live);
+
+    let threads: Vec<_> = (0..4)
+        .map(|_| std::thread::spawn(|| vec![1u64; 1 << 16].len()))
+        .collect();
+
+    for thread in threads {
+        assert_eq!(thread.join().unwrap(), 1 << 16);
+    }
+
+    assert!(HEAP.remaining() > 60 << 20);
+}
@@ -0,0 +1,23 @@
+#![cfg(feature = "nightly")]
+#![feature(allocator_api)]
+
+use aliu::*;
+
+#[test]
+fn test_std_interop() {
+    let mut bucket_list = BucketList::new();
+
+    {
+        let scoped = bucket_list.scoped();
+        let mut v = Vec::new_in(&scoped);
+        v.extend_from_slice(&[1u32, 2, 3]);
+
+        let b = Box::new_in(12u64, StdAllocator(&scoped));
+        assert_eq!(*b, 12);
+        assert_eq!(&[1, 2, 3], &v[..]);
+    }
+
+    let mut pod = Pod::<u32, _>::with_allocator(FromStdAllocator(std::alloc::Global));
+    pod.push_repeat(7, 3);
+    assert_eq!(&[7, 7, 7], &pod[..]);
+}